@@ -1,7 +1,14 @@
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime};
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
-use crate::{exporter::FormatEnum, task::PriorityEnum};
+use crate::{
+    color::{ColorByEnum, ColorEnum},
+    duration::parse_duration,
+    exporter::FormatEnum,
+    store::BackendEnum,
+    task::{FieldEnum, PriorityEnum, RecurrenceEnum, SortEnum, StatusEnum, TimestampEnum},
+};
 
 #[derive(Parser)]
 #[command(name = "todo")]
@@ -12,19 +19,69 @@ pub struct Cli {
     /// Path to the save file
     #[arg(short, long, default_value = "todo.json")]
     pub path: PathBuf,
+    /// Path to the config file
+    #[arg(long, default_value = "todo.toml")]
+    pub config: PathBuf,
+    /// How to store the tasks, guessed from the path extension by default
+    #[arg(long, value_enum)]
+    pub backend: Option<BackendEnum>,
+    /// Fail instead of ignoring unknown fields in the save file
+    #[arg(long)]
+    pub strict: bool,
+    /// Do not print the reminders of pending tasks
+    #[arg(long)]
+    pub no_reminders: bool,
+    /// When to color the output
+    #[arg(long, value_enum, default_value_t = ColorEnum::Auto)]
+    pub color: ColorEnum,
+    /// Seed the random choices, to repeat them
+    #[arg(long, global = true)]
+    pub seed: Option<u64>,
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
     /// Add a new task
+    #[command(visible_alias = "new")]
     Add {
-        /// The task title
+        /// The task title, one task per line with --split-lines
         title: String,
+        /// Add one task per non-empty line of a multi-line title, instead of refusing it
+        #[arg(long)]
+        split_lines: bool,
         /// The task priority
-        #[arg(short, long)]
+        #[arg(short, long, ignore_case = true)]
         priority: Option<PriorityEnum>,
+        /// The due date (YYYY-MM-DD)
+        #[arg(long, value_parser = parse_date)]
+        due: Option<DateTime<Local>>,
+        /// Repeat the task on this schedule
+        #[arg(long)]
+        every: Option<RecurrenceEnum>,
+        /// A longer description shown under the title
+        #[arg(short, long)]
+        description: Option<String>,
+        /// The device or project creating the task, the hostname by default
+        #[arg(long)]
+        source: Option<String>,
+        /// When to be reminded of the task (YYYY-MM-DD HH:MM)
+        #[arg(long, value_parser = parse_datetime)]
+        remind: Option<DateTime<Local>>,
+        /// Make the task a subtask of this task ID
+        #[arg(long, value_parser = parse_id)]
+        parent: Option<i32>,
+        /// Tag the task, can be repeated
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        /// Link the task to a URL, can be repeated
+        #[arg(long = "link")]
+        links: Vec<String>,
+        /// Do not list the tasks afterwards
+        #[arg(long)]
+        silent: bool,
     },
     /// List all tasks
+    #[command(visible_alias = "ls")]
     List {
         /// Display only completed tasks
         #[arg(long)]
@@ -33,23 +90,599 @@ pub enum Commands {
         /// Display only pending tasks
         #[arg(long)]
         pending: bool,
+
+        /// Display only these fields, in order (e.g. id,title,due)
+        #[arg(long, value_delimiter = ',')]
+        fields: Vec<FieldEnum>,
+
+        /// Sort the tasks
+        #[arg(long)]
+        sort: Option<SortEnum>,
+
+        /// Put tasks without a priority first when sorting by priority
+        #[arg(long)]
+        none_first: bool,
+
+        /// Show at most this many tasks, after filtering and sorting
+        #[arg(long)]
+        limit: Option<usize>,
+
+        /// Do not group subtasks under their parent
+        #[arg(long)]
+        flat: bool,
+
+        /// Display only tasks with an id greater than this one
+        #[arg(long, value_parser = parse_id)]
+        after_id: Option<i32>,
+
+        /// Display only tasks with an id less than this one
+        #[arg(long, value_parser = parse_id)]
+        before_id: Option<i32>,
+
+        /// Also show the tasks deferred to a later date
+        #[arg(long)]
+        show_deferred: bool,
+
+        /// Color whole rows by priority, status, first tag or due date, when color is on
+        #[arg(long, value_enum)]
+        color_by: Option<ColorByEnum>,
+
+        /// Display only tasks created from this device or project
+        #[arg(long)]
+        source: Option<String>,
+
+        /// Display only tasks with this status
+        #[arg(long, ignore_case = true)]
+        status: Option<StatusEnum>,
+
+        /// Display only tasks with this tag, can be repeated to require several
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+
+        /// Hide tasks with this tag, can be repeated, winning over --tag
+        #[arg(long = "exclude-tag")]
+        excluded_tags: Vec<String>,
+
+        /// Display only tasks changed today, whatever their status
+        #[arg(long)]
+        modified_today: bool,
+
+        /// Display only tasks with a due date
+        #[arg(long)]
+        has_due: bool,
+
+        /// Display only tasks without a due date
+        #[arg(long, conflicts_with_all = ["has_due", "due_within"])]
+        no_due: bool,
+
+        /// Display only pending tasks due from now until this duration (e.g. 3d, 1w)
+        #[arg(long, value_parser = parse_duration)]
+        due_within: Option<Duration>,
+
+        /// Display only tasks completed between these dates, inclusive (YYYY-MM-DD YYYY-MM-DD)
+        #[arg(long, num_args = 2, value_names = ["START", "END"])]
+        done_between: Option<Vec<NaiveDate>>,
+
+        /// Print the tasks as compact JSON, wrapped in an object with a version and count
+        #[arg(long, conflicts_with_all = ["json_pretty", "json_array", "porcelain", "null"])]
+        json: bool,
+
+        /// Like --json, indented
+        #[arg(long, conflicts_with_all = ["json_array", "porcelain", "null"])]
+        json_pretty: bool,
+
+        /// Print the tasks as a bare compact JSON array
+        #[arg(long, conflicts_with_all = ["porcelain", "null"])]
+        json_array: bool,
+
+        /// Print one line per task for scripts, fields split by the separator
+        #[arg(long, conflicts_with = "null")]
+        porcelain: bool,
+
+        /// Like --porcelain, each task ending with a NUL byte instead of a newline
+        #[arg(long)]
+        null: bool,
+
+        /// The field separator of --porcelain and --null
+        #[arg(long, default_value = "\t", value_parser = parse_separator)]
+        separator: String,
+
+        /// Write the listing to this file instead of stdout, creating its directories
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Break a task down into subtasks
+    Split {
+        /// The task ID
+        #[arg(value_parser = parse_id)]
+        id: i32,
+        /// The subtask titles
+        #[arg(required = true, value_parser = parse_title)]
+        titles: Vec<String>,
+    },
+    /// Change the title of a task
+    Edit {
+        /// The task ID
+        #[arg(value_parser = parse_id)]
+        id: i32,
+        /// The new title
+        #[arg(value_parser = parse_title)]
+        title: String,
+    },
+    /// Hide a task from list until a date, unlike a due date
+    Defer {
+        /// The task ID
+        #[arg(value_parser = parse_id)]
+        id: i32,
+        /// Show the task again from then (YYYY-MM-DD or "YYYY-MM-DD HH:MM"), now when omitted
+        #[arg(value_parser = parse_datetime)]
+        until: Option<DateTime<Local>>,
+    },
+    /// Reset the creation date of a task to now
+    Touch {
+        /// The task ID
+        #[arg(value_parser = parse_id)]
+        id: i32,
+    },
+    /// Name the list, shown above the tasks
+    SetName {
+        /// The list name
+        name: String,
+        /// A description shown under the name
+        #[arg(short, long)]
+        description: Option<String>,
+    },
+    /// Set the status of a task
+    SetStatus {
+        /// The task ID
+        #[arg(value_parser = parse_id)]
+        id: i32,
+        /// The new status
+        #[arg(ignore_case = true)]
+        status: StatusEnum,
+    },
+    /// Add or remove tags on a task
+    Tag {
+        /// The task ID
+        #[arg(value_parser = parse_id)]
+        id: i32,
+        /// Tags to add, can be repeated
+        #[arg(long)]
+        add: Vec<String>,
+        /// Tags to remove, can be repeated
+        #[arg(long)]
+        remove: Vec<String>,
+    },
+    /// Add or remove links on a task
+    Link {
+        /// The task ID
+        #[arg(value_parser = parse_id)]
+        id: i32,
+        /// Links to add, can be repeated
+        #[arg(long)]
+        add: Vec<String>,
+        /// Links to remove, can be repeated
+        #[arg(long)]
+        remove: Vec<String>,
+    },
+    /// Show every detail of a task
+    Show {
+        /// The task ID
+        #[arg(value_parser = parse_id)]
+        id: i32,
+    },
+    /// Move a task to another list file, where it gets a new ID
+    Transfer {
+        /// The task ID
+        #[arg(value_parser = parse_id)]
+        id: i32,
+        /// The list file receiving the task
+        to: PathBuf,
     },
     /// Remove a task
+    #[command(visible_aliases = ["rm", "del"])]
     Remove {
         /// The task ID
+        #[arg(value_parser = parse_id)]
         id: i32,
+        /// Do not list the tasks afterwards
+        #[arg(long)]
+        silent: bool,
     },
-    /// Complete a task
+    /// Complete one or more tasks
+    #[command(visible_alias = "done")]
     Complete {
+        /// The task IDs
+        #[arg(required = true, value_parser = parse_id)]
+        ids: Vec<i32>,
+        /// Remove the tasks once completed, the completion staying in the history
+        #[arg(long)]
+        remove: bool,
+        /// Do not ask for confirmation when a task has pending subtasks
+        #[arg(short, long)]
+        yes: bool,
+        /// Append the tasks to the day's done-YYYY-MM-DD.md journal, as the journal setting does
+        #[arg(long)]
+        journal: bool,
+        /// Also complete a parent once its last pending subtask is completed
+        #[arg(long)]
+        cascade_parent: bool,
+        /// Reopen this task, completed by mistake, while completing the given one
+        #[arg(long, value_name = "ID", value_parser = parse_id, conflicts_with = "remove")]
+        instead_of: Option<i32>,
+        /// Do not list the tasks afterwards
+        #[arg(long)]
+        silent: bool,
+    },
+    /// Set how far along a task is
+    Progress {
+        /// The task ID
+        #[arg(value_parser = parse_id)]
+        id: i32,
+        /// The progress, in percent
+        #[arg(value_parser = clap::value_parser!(u8).range(0..=100))]
+        percent: u8,
+    },
+    /// Raise how far along a task is, completing it at 100%
+    AddProgress {
+        /// The task ID
+        #[arg(value_parser = parse_id)]
+        id: i32,
+        /// The progress to add, in percent
+        #[arg(value_parser = clap::value_parser!(u8).range(0..=100))]
+        percent: u8,
+    },
+    /// Show completion statistics
+    Stats {
+        /// Print the figures as a JSON object
+        #[arg(long)]
+        json: bool,
+
+        /// Chart the number of tasks completed on each of the last N days instead
+        #[arg(long, value_name = "N", conflicts_with = "json")]
+        by_day: Option<u32>,
+    },
+    /// Start tracking time on a task
+    Start {
+        /// The task ID
+        #[arg(value_parser = parse_id)]
+        id: i32,
+    },
+    /// Stop tracking time on a task
+    Stop {
         /// The task ID
+        #[arg(value_parser = parse_id)]
         id: i32,
     },
+    /// Complete every pending task
+    CompleteAll {
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+        /// Append the tasks to the day's done-YYYY-MM-DD.md journal, as the journal setting does
+        #[arg(long)]
+        journal: bool,
+    },
+    /// Delete every task, after typing the list name or PURGE to confirm
+    Purge {
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Go through the pending tasks one at a time, choosing what to do with each
+    Review,
+    /// Pick a pending task at random
+    Random {
+        /// Make high priority tasks three times and medium ones twice as likely as the others
+        #[arg(long)]
+        weighted: bool,
+    },
+    /// List pending tasks with the same title
+    Duplicates {
+        /// Keep only the oldest task of each group
+        #[arg(long)]
+        remove: bool,
+    },
+    /// Reassign task ids from 1 in the current order
+    RenumberIds,
+    /// Print the log of every add, complete, remove and edit
+    History,
     /// Reset all tasks
     Reset,
+    /// Delete completed tasks
+    Clear {
+        /// Only delete tasks completed before this date (YYYY-MM-DD [HH:MM])
+        #[arg(long, value_parser = parse_datetime)]
+        before: Option<DateTime<Local>>,
+    },
+    /// Move completed tasks to the archive file
+    Archive,
+    /// Copy the save file to a timestamped backup
+    Backup,
+    /// Restore a backup over the save file, listing backups when none is given
+    RestoreBackup {
+        /// The backup to restore, the latest one by default
+        #[arg(long)]
+        which: Option<String>,
+    },
+    /// Print a one-line summary of done, pending and overdue tasks
+    Status {
+        /// Omit the trailing newline, to embed in a shell prompt
+        #[arg(long)]
+        prompt: bool,
+    },
+    /// Chart the number of open tasks over the last days
+    Burndown {
+        /// How many days to chart
+        #[arg(default_value_t = 14)]
+        days: u32,
+    },
+    /// Preview the next due dates of a recurring task
+    Forecast {
+        /// The task ID
+        #[arg(value_parser = parse_id)]
+        id: i32,
+        /// How many occurrences to show
+        #[arg(default_value_t = 5)]
+        count: usize,
+    },
     /// Export all tasks
     Export {
         /// Choose which format to export to
-        #[arg(short, long)]
+        #[arg(short, long, ignore_case = true)]
         format: FormatEnum,
+        /// Export to this file instead of next to the save file
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Add to the end of the file instead of replacing it (CSV, Markdown and JSONL)
+        #[arg(long)]
+        append: bool,
+        /// Order the exported tasks, whatever the order of the list
+        #[arg(long)]
+        sort: Option<SortEnum>,
+        /// Export even when the target is the save file
+        #[arg(long)]
+        force: bool,
+        /// Indent JSON exports by this many spaces, or with tabs (`tab`)
+        #[arg(long, value_parser = parse_indent)]
+        indent: Option<String>,
+        /// How precisely CSV and Markdown exports show dates, the timestamps setting when omitted
+        #[arg(long, value_enum)]
+        timestamps: Option<TimestampEnum>,
+    },
+    /// Check a todo file parses strictly and is consistent, exiting with 1 when not, without changing it
+    Validate {
+        /// The todo file to check
+        file: PathBuf,
+    },
+    /// Compare with another todo file
+    Diff {
+        /// The other todo file
+        other: PathBuf,
     },
+    /// Import the tasks of another file
+    Import {
+        /// The file to import from
+        file: PathBuf,
+        /// The format of the imported file
+        #[arg(long, default_value = "json", ignore_case = true)]
+        input_format: FormatEnum,
+    },
+}
+
+/// Parse a `YYYY-MM-DD` date as the end of that day in local time
+pub fn parse_date(value: &str) -> Result<DateTime<Local>, String> {
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|e| format!("invalid date '{}': {}", value, e))?;
+    end_of_day(date).ok_or_else(|| format!("invalid date '{}'", value))
+}
+
+/// Parse a `YYYY-MM-DD HH:MM` local date and time, or a `YYYY-MM-DD` date at midnight
+pub fn parse_datetime(value: &str) -> Result<DateTime<Local>, String> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M")
+        .or_else(|_| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d").map(|date| date.and_time(NaiveTime::MIN))
+        })
+        .map_err(|e| format!("invalid date '{}': {}", value, e))?
+        .and_local_timezone(Local)
+        .earliest()
+        .ok_or_else(|| format!("invalid date '{}'", value))
+}
+
+/// Parse a task id, which starts at 1
+pub fn parse_id(value: &str) -> Result<i32, String> {
+    match value.parse::<i32>() {
+        Ok(id) if id > 0 => Ok(id),
+        Ok(_) => Err("task ids start at 1".to_string()),
+        Err(_) => Err(format!("invalid task id '{}'", value)),
+    }
+}
+
+pub fn parse_separator(value: &str) -> Result<String, String> {
+    if value.contains(['\n', '\r']) {
+        return Err("the separator cannot contain a newline".to_string());
+    }
+    Ok(value.to_string())
+}
+
+/// A title on a single line, newlines breaking the Markdown and CSV exports
+pub fn parse_title(value: &str) -> Result<String, String> {
+    if value.contains(['\n', '\r']) {
+        return Err("the title cannot contain a newline".to_string());
+    }
+    Ok(value.to_string())
+}
+
+/// The trimmed non-empty lines of a pasted title, each the title of a task
+pub fn title_lines(title: &str) -> Vec<String> {
+    title
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// A number of spaces, up to 16, or `tab`
+pub fn parse_indent(value: &str) -> Result<String, String> {
+    if value.eq_ignore_ascii_case("tab") {
+        return Ok("\t".to_string());
+    }
+    match value.parse::<usize>() {
+        Ok(width) if width <= 16 => Ok(" ".repeat(width)),
+        _ => Err("expected a number of spaces up to 16, or tab".to_string()),
+    }
+}
+
+/// The width used when stdout is a terminal whose size cannot be read
+pub const DEFAULT_WIDTH: usize = 80;
+
+/// The width to wrap output to, `None` when stdout is not a TTY so piped output is left whole
+pub fn effective_width(is_terminal: bool, detected: Option<usize>) -> Option<usize> {
+    is_terminal.then(|| detected.unwrap_or(DEFAULT_WIDTH))
+}
+
+pub fn end_of_day(date: NaiveDate) -> Option<DateTime<Local>> {
+    date.and_hms_opt(23, 59, 59)?
+        .and_local_timezone(Local)
+        .earliest()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_aliases() {
+        for (value, expected) in [("yml", "Yaml"), ("yaml", "Yaml"), ("md", "Markdown")] {
+            let cli = Cli::try_parse_from(["todo", "export", "--format", value]).unwrap();
+            let Commands::Export { format, .. } = cli.command else {
+                panic!("expected the export command");
+            };
+            assert_eq!(format!("{:?}", format), expected);
+        }
+    }
+
+    #[test]
+    fn test_command_aliases() {
+        let parse = |args: &[&str]| Cli::try_parse_from(args).unwrap().command;
+        assert!(matches!(parse(&["todo", "ls"]), Commands::List { .. }));
+        assert!(matches!(
+            parse(&["todo", "rm", "1"]),
+            Commands::Remove { id: 1, .. }
+        ));
+        assert!(matches!(
+            parse(&["todo", "del", "2"]),
+            Commands::Remove { id: 2, .. }
+        ));
+        assert!(matches!(
+            parse(&["todo", "done", "1", "3"]),
+            Commands::Complete { ids, .. } if ids == [1, 3]
+        ));
+        assert!(matches!(
+            parse(&["todo", "new", "buy milk"]),
+            Commands::Add { title, .. } if title == "buy milk"
+        ));
+    }
+
+    #[test]
+    fn test_invalid_format_lists_supported_formats() {
+        let error = Cli::try_parse_from(["todo", "export", "--format", "jsom"])
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(error.contains("json, csv, yaml, markdown"));
+    }
+
+    #[test]
+    fn test_priority_any_case() {
+        for value in ["high", "High", "HIGH", "hIgH"] {
+            let cli = Cli::try_parse_from(["todo", "add", "task", "--priority", value]).unwrap();
+            let Commands::Add { priority, .. } = cli.command else {
+                panic!("expected the add command");
+            };
+            assert_eq!(priority, Some(PriorityEnum::High));
+        }
+    }
+
+    #[test]
+    fn test_format_any_case() {
+        for (value, expected) in [("CSV", "Csv"), ("Json", "Json"), ("YML", "Yaml")] {
+            let cli = Cli::try_parse_from(["todo", "export", "--format", value]).unwrap();
+            let Commands::Export { format, .. } = cli.command else {
+                panic!("expected the export command");
+            };
+            assert_eq!(format!("{:?}", format), expected);
+
+            let cli =
+                Cli::try_parse_from(["todo", "import", "file", "--input-format", value]).unwrap();
+            let Commands::Import { input_format, .. } = cli.command else {
+                panic!("expected the import command");
+            };
+            assert_eq!(format!("{:?}", input_format), expected);
+        }
+    }
+
+    #[test]
+    fn test_separator_rejects_newlines() {
+        let cli = Cli::try_parse_from(["todo", "list", "--porcelain", "--separator", "|"]).unwrap();
+        let Commands::List { separator, .. } = cli.command else {
+            panic!("expected the list command");
+        };
+        assert_eq!(separator, "|");
+
+        assert!(
+            Cli::try_parse_from(["todo", "list", "--porcelain", "--separator", "a\nb"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_ids_must_be_positive() {
+        for command in ["complete", "remove"] {
+            for id in ["0", "-1", "99999999999", "one"] {
+                assert!(Cli::try_parse_from(["todo", command, id]).is_err());
+            }
+            assert!(Cli::try_parse_from(["todo", command, "2147483647"]).is_ok());
+        }
+        let cli = Cli::try_parse_from(["todo", "complete", "3", "5"]).unwrap();
+        let Commands::Complete { ids, .. } = cli.command else {
+            panic!("expected the complete command");
+        };
+        assert_eq!(ids, vec![3, 5]);
+    }
+
+    #[test]
+    fn test_parse_indent() {
+        assert_eq!(parse_indent("4"), Ok("    ".to_string()));
+        assert_eq!(parse_indent("0"), Ok(String::new()));
+        assert_eq!(parse_indent("tab"), Ok("\t".to_string()));
+        assert!(parse_indent("17").is_err());
+        assert!(parse_indent("-2").is_err());
+    }
+
+    #[test]
+    fn test_has_due_and_no_due_are_exclusive() {
+        assert!(Cli::try_parse_from(["todo", "list", "--has-due"]).is_ok());
+        assert!(Cli::try_parse_from(["todo", "list", "--no-due"]).is_ok());
+        assert!(Cli::try_parse_from(["todo", "list", "--has-due", "--no-due"]).is_err());
+        assert!(Cli::try_parse_from(["todo", "list", "--no-due", "--due-within", "3d"]).is_err());
+    }
+
+    #[test]
+    fn test_title_lines() {
+        assert_eq!(
+            title_lines("buy milk\r\n\n  call bob  \n"),
+            vec!["buy milk", "call bob"]
+        );
+        assert!(parse_title("buy milk\ncall bob").is_err());
+        assert!(Cli::try_parse_from(["todo", "edit", "1", "a\nb"]).is_err());
+    }
+
+    #[test]
+    fn test_effective_width() {
+        assert_eq!(effective_width(true, Some(120)), Some(120));
+        assert_eq!(effective_width(true, None), Some(DEFAULT_WIDTH));
+        assert_eq!(effective_width(false, Some(120)), None);
+        assert_eq!(effective_width(false, None), None);
+    }
 }
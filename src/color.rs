@@ -0,0 +1,113 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+
+pub const RED: &str = "\x1b[31m";
+pub const GREEN: &str = "\x1b[32m";
+pub const YELLOW: &str = "\x1b[33m";
+pub const BLUE: &str = "\x1b[34m";
+pub const MAGENTA: &str = "\x1b[35m";
+pub const CYAN: &str = "\x1b[36m";
+pub const WHITE: &str = "\x1b[37m";
+const RESET: &str = "\x1b[0m";
+
+/// A terminal color a theme can pick
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum PaletteEnum {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+}
+
+impl PaletteEnum {
+    pub fn code(&self) -> &'static str {
+        match self {
+            PaletteEnum::Red => RED,
+            PaletteEnum::Green => GREEN,
+            PaletteEnum::Yellow => YELLOW,
+            PaletteEnum::Blue => BLUE,
+            PaletteEnum::Magenta => MAGENTA,
+            PaletteEnum::Cyan => CYAN,
+            PaletteEnum::White => WHITE,
+        }
+    }
+}
+
+/// Colors of task titles by status and of priorities, from the `[theme]` config section
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Theme {
+    pub done: PaletteEnum,
+    pub pending: PaletteEnum,
+    pub high: PaletteEnum,
+    pub medium: PaletteEnum,
+    pub low: PaletteEnum,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            done: PaletteEnum::Green,
+            pending: PaletteEnum::Red,
+            high: PaletteEnum::Red,
+            medium: PaletteEnum::Yellow,
+            low: PaletteEnum::Green,
+        }
+    }
+}
+
+/// What colors whole rows of the task list
+#[derive(Clone, Copy, ValueEnum, PartialEq, Debug)]
+pub enum ColorByEnum {
+    Priority,
+    /// Todo, doing, waiting and done
+    Status,
+    /// The first tag, each tag always getting the same color
+    Tag,
+    /// Overdue, due within `DUE_SOON_DAYS` days or due later
+    Due,
+}
+
+/// How close a due date is to count as soon when coloring by due date
+pub const DUE_SOON_DAYS: i64 = 3;
+
+/// The colors given to tags, picked by a hash of the tag
+const TAG_COLORS: [&str; 6] = [RED, GREEN, YELLOW, BLUE, MAGENTA, CYAN];
+
+/// The color of a tag, the same on every run
+pub fn tag_color(tag: &str) -> &'static str {
+    let hash = tag.bytes().fold(0usize, |hash, byte| {
+        hash.wrapping_mul(31).wrapping_add(byte.into())
+    });
+    TAG_COLORS[hash % TAG_COLORS.len()]
+}
+
+#[derive(Clone, Copy, ValueEnum, PartialEq, Debug)]
+pub enum ColorEnum {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorEnum {
+    /// Resolve the mode to a yes/no, `is_terminal` telling whether stdout is a TTY
+    pub fn enabled(&self, is_terminal: bool) -> bool {
+        match self {
+            ColorEnum::Auto => is_terminal,
+            ColorEnum::Always => true,
+            ColorEnum::Never => false,
+        }
+    }
+}
+
+pub fn paint(text: &str, code: &str, enabled: bool) -> String {
+    if enabled {
+        format!("{}{}{}", code, text, RESET)
+    } else {
+        text.to_string()
+    }
+}
@@ -0,0 +1,186 @@
+use chrono::{DateTime, Days, Local};
+use serde::Deserialize;
+use std::{fmt, fs, io, path::Path};
+
+use crate::{
+    cli::end_of_day,
+    color::Theme,
+    task::{PriorityEnum, TimestampEnum},
+};
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct Config {
+    /// Archive tasks completed more than this many days ago on load, 0 disables it
+    pub auto_archive_days: u32,
+    /// Raise the priority of pending tasks older than this many days on load, 0 disables it
+    pub auto_escalate_days: u32,
+    pub default_due_days: DefaultDueDays,
+    pub theme: Theme,
+    /// Which tasks a bare `list` shows
+    pub default_list_filter: ListFilterEnum,
+    /// Unix permissions of the save file (e.g. 0o600), the umask decides when unset
+    pub file_mode: Option<u32>,
+    /// Keep only this many completed tasks, pruning the least recently completed on save
+    pub max_completed: Option<usize>,
+    /// Move the tasks pruned by `max_completed` to the archive file instead of deleting them
+    pub archive_pruned: bool,
+    /// Append each completed task to `done-YYYY-MM-DD.md` next to the save file
+    pub journal: bool,
+    /// How precisely the list and the CSV and Markdown exports show dates
+    pub timestamps: TimestampEnum,
+}
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ListFilterEnum {
+    #[default]
+    All,
+    Pending,
+    Completed,
+}
+
+/// Days from now until a task of each priority is due, when added without `--due`
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct DefaultDueDays {
+    pub high: Option<u64>,
+    pub medium: Option<u64>,
+    pub low: Option<u64>,
+}
+
+pub enum ConfigError {
+    DeserializationError(String),
+    IoError(io::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::DeserializationError(msg) => write!(f, "Invalid config {}", msg),
+            ConfigError::IoError(e) => write!(f, "IO error {}", e),
+        }
+    }
+}
+
+impl Config {
+    pub fn default_due(
+        &self,
+        priority: Option<&PriorityEnum>,
+        now: DateTime<Local>,
+    ) -> Option<DateTime<Local>> {
+        let days = match priority? {
+            PriorityEnum::High => self.default_due_days.high,
+            PriorityEnum::Medium => self.default_due_days.medium,
+            PriorityEnum::Low => self.default_due_days.low,
+        }?;
+        end_of_day(now.date_naive().checked_add_days(Days::new(days))?)
+    }
+
+    /// Load the config file, a missing file meaning the defaults
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        match fs::read_to_string(path) {
+            Ok(content) => toml::from_str(&content)
+                .map_err(|e| ConfigError::DeserializationError(e.to_string())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(ConfigError::IoError(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        color::{PaletteEnum, RED},
+        task::{Task, TimestampEnum},
+    };
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_default_due_per_priority() {
+        let config: Config = toml::from_str(
+            r#"
+            [default_due_days]
+            high = 1
+            low = 7
+            "#,
+        )
+        .unwrap();
+        let now = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        assert_eq!(
+            config.default_due(Some(&PriorityEnum::High), now),
+            Some(Local.with_ymd_and_hms(2024, 6, 2, 23, 59, 59).unwrap())
+        );
+        assert_eq!(
+            config.default_due(Some(&PriorityEnum::Low), now),
+            Some(Local.with_ymd_and_hms(2024, 6, 8, 23, 59, 59).unwrap())
+        );
+        assert_eq!(config.default_due(Some(&PriorityEnum::Medium), now), None);
+        assert_eq!(config.default_due(None, now), None);
+    }
+
+    #[test]
+    fn test_max_completed_defaults_to_unlimited() {
+        let config: Config = toml::from_str("max_completed = 50").unwrap();
+        assert_eq!(config.max_completed, Some(50));
+        assert!(!config.archive_pruned);
+        assert_eq!(Config::default().max_completed, None);
+    }
+
+    #[test]
+    fn test_timestamps_setting() {
+        let config: Config = toml::from_str(r#"timestamps = "date""#).unwrap();
+        assert_eq!(config.timestamps, TimestampEnum::Date);
+        assert_eq!(Config::default().timestamps, TimestampEnum::Datetime);
+    }
+
+    #[test]
+    fn test_file_mode_octal() {
+        let config: Config = toml::from_str("file_mode = 0o600").unwrap();
+        assert_eq!(config.file_mode, Some(0o600));
+        assert_eq!(Config::default().file_mode, None);
+    }
+
+    #[test]
+    fn test_custom_theme() {
+        let config: Config = toml::from_str(
+            r#"
+            [theme]
+            pending = "blue"
+            high = "magenta"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.theme.pending, PaletteEnum::Blue);
+        assert_eq!(config.theme.done, PaletteEnum::Green);
+
+        let task = Task::new(1, "task".to_string(), Some(PriorityEnum::High));
+        let line = task.render(true, &config.theme, TimestampEnum::Datetime, 1, Some(80));
+        assert!(line.contains("\x1b[34mtask"));
+        assert!(line.contains("\x1b[35m - Priority high"));
+        assert!(!line.contains(RED));
+
+        let line = task.render(
+            true,
+            &Theme::default(),
+            TimestampEnum::Datetime,
+            1,
+            Some(80),
+        );
+        assert!(line.contains("\x1b[31mtask"));
+    }
+
+    #[test]
+    fn test_unknown_theme_color() {
+        let result: Result<Config, _> = toml::from_str("[theme]\ndone = \"pink\"");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_default_list_filter() {
+        let config: Config = toml::from_str(r#"default_list_filter = "pending""#).unwrap();
+        assert_eq!(config.default_list_filter, ListFilterEnum::Pending);
+        assert_eq!(Config::default().default_list_filter, ListFilterEnum::All);
+    }
+}
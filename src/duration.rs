@@ -0,0 +1,132 @@
+use chrono::Duration;
+use std::{error, fmt};
+
+#[derive(PartialEq, Debug)]
+pub enum DurationError {
+    Empty,
+    MissingUnit(String),
+    InvalidAmount(String),
+    UnknownUnit(String),
+    Overflow,
+}
+
+impl fmt::Display for DurationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DurationError::Empty => write!(f, "empty duration"),
+            DurationError::MissingUnit(amount) => write!(f, "missing unit after '{}'", amount),
+            DurationError::InvalidAmount(amount) => write!(f, "invalid amount '{}'", amount),
+            DurationError::UnknownUnit(unit) => {
+                write!(f, "unknown unit '{}', expected one of s, m, h, d, w", unit)
+            }
+            DurationError::Overflow => write!(f, "duration is too large"),
+        }
+    }
+}
+
+impl error::Error for DurationError {}
+
+/// Parse a duration like `30m`, `12h`, `3d`, `2w` or a combination like `1w2d`
+pub fn parse_duration(value: &str) -> Result<Duration, DurationError> {
+    let mut rest = value.trim();
+    if rest.is_empty() {
+        return Err(DurationError::Empty);
+    }
+    let mut total = Duration::zero();
+    while !rest.is_empty() {
+        let unit_start = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(|| DurationError::MissingUnit(rest.to_string()))?;
+        let (amount, tail) = rest.split_at(unit_start);
+        let unit_end = tail
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_end);
+        if amount.is_empty() {
+            return Err(DurationError::InvalidAmount(unit.to_string()));
+        }
+        let amount: i64 = amount.parse().map_err(|_| DurationError::Overflow)?;
+        let part = match unit {
+            "s" => Duration::try_seconds(amount),
+            "m" => Duration::try_minutes(amount),
+            "h" => Duration::try_hours(amount),
+            "d" => Duration::try_days(amount),
+            "w" => Duration::try_weeks(amount),
+            _ => return Err(DurationError::UnknownUnit(unit.to_string())),
+        }
+        .ok_or(DurationError::Overflow)?;
+        total = total.checked_add(&part).ok_or(DurationError::Overflow)?;
+        rest = tail;
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_single_units() {
+        assert_eq!(parse_duration("45s"), Ok(Duration::seconds(45)));
+        assert_eq!(parse_duration("30m"), Ok(Duration::minutes(30)));
+        assert_eq!(parse_duration("12h"), Ok(Duration::hours(12)));
+        assert_eq!(parse_duration("3d"), Ok(Duration::days(3)));
+        assert_eq!(parse_duration(" 2w "), Ok(Duration::weeks(2)));
+        assert_eq!(parse_duration("0d"), Ok(Duration::zero()));
+    }
+
+    #[test]
+    fn test_parse_combinations() {
+        assert_eq!(parse_duration("1w2d"), Ok(Duration::days(9)));
+        assert_eq!(
+            parse_duration("1h30m"),
+            Ok(Duration::hours(1) + Duration::minutes(30))
+        );
+        assert_eq!(parse_duration("1d1d"), Ok(Duration::days(2)));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert_eq!(parse_duration(""), Err(DurationError::Empty));
+        assert_eq!(
+            parse_duration("12"),
+            Err(DurationError::MissingUnit("12".to_string()))
+        );
+        assert_eq!(
+            parse_duration("1w2"),
+            Err(DurationError::MissingUnit("2".to_string()))
+        );
+        assert_eq!(
+            parse_duration("d"),
+            Err(DurationError::InvalidAmount("d".to_string()))
+        );
+        assert_eq!(
+            parse_duration("-3d"),
+            Err(DurationError::InvalidAmount("-".to_string()))
+        );
+        assert_eq!(
+            parse_duration("3y"),
+            Err(DurationError::UnknownUnit("y".to_string()))
+        );
+        assert_eq!(
+            parse_duration("3 d"),
+            Err(DurationError::UnknownUnit(" d".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_overflow() {
+        assert_eq!(
+            parse_duration("99999999999999999999s"),
+            Err(DurationError::Overflow)
+        );
+        assert_eq!(
+            parse_duration("9999999999999999w"),
+            Err(DurationError::Overflow)
+        );
+        assert_eq!(
+            parse_duration("100000000000d100000000000d"),
+            Err(DurationError::Overflow)
+        );
+    }
+}
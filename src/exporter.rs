@@ -1,81 +1,651 @@
+use chrono::{DateTime, Local};
 use clap::ValueEnum;
-use std::fs;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
 
-use crate::todolist::TodoList;
+use crate::{
+    task::{PriorityEnum, RecurrenceEnum, SortEnum, StatusEnum, Task, TimestampEnum},
+    todolist::TodoList,
+};
 
-#[derive(Clone, ValueEnum)]
+#[derive(Clone, ValueEnum, Debug)]
 pub enum FormatEnum {
+    /// JSON document
     Json,
+    /// One row per task
     Csv,
+    /// YAML document
+    #[value(alias = "yml")]
     Yaml,
+    /// Checklist grouped by status
+    #[value(alias = "md")]
     Markdown,
+    /// One JSON task per line
+    Jsonl,
+    /// GitHub task list for issue bodies, subtasks nested and without dates
+    Gfm,
+}
+
+impl FormatEnum {
+    /// Where to export when no path is given, next to the save file with the format's extension
+    pub fn default_path(&self, path: &Path) -> PathBuf {
+        match self {
+            FormatEnum::Json => path.with_extension("json"),
+            FormatEnum::Csv => path.with_extension("csv"),
+            FormatEnum::Yaml => path.with_extension("yaml"),
+            FormatEnum::Markdown => path.with_extension("md"),
+            FormatEnum::Jsonl => path.with_extension("jsonl"),
+            FormatEnum::Gfm => path.with_extension("gfm.md"),
+        }
+    }
+
+    /// The exporter of the format, with its default options
+    pub fn exporter(&self) -> Box<dyn Exporter> {
+        match self {
+            FormatEnum::Json => Box::new(JsonExporter::default()),
+            FormatEnum::Csv => Box::new(CsvExporter),
+            FormatEnum::Yaml => Box::new(YamlExporter),
+            FormatEnum::Markdown => Box::new(MarkdownExporter),
+            FormatEnum::Jsonl => Box::new(JsonlExporter),
+            FormatEnum::Gfm => Box::new(GfmExporter),
+        }
+    }
+}
+
+/// How an export is written, on top of its format
+#[derive(Default, Debug)]
+pub struct ExportOptions {
+    /// Export to this file instead of next to the save file
+    pub out: Option<PathBuf>,
+    /// Add to the end of the file instead of replacing it
+    pub append: bool,
+    /// Order the exported tasks, leaving the list itself untouched
+    pub sort: Option<SortEnum>,
+    /// Write even over the save file
+    pub force: bool,
+    /// Indentation of JSON exports, two spaces when unset
+    pub indent: Option<String>,
 }
 
 pub trait Exporter {
-    fn export(&self, todolist: &TodoList) -> Result<(), ExportError>;
+    /// Write the whole export to `out`
+    fn write(&self, todolist: &TodoList, out: &mut dyn Write) -> Result<(), ExportError>;
+
+    /// Write the export to a file, replacing it
+    fn export_to_path(&self, todolist: &TodoList, path: &Path) -> Result<(), ExportError> {
+        let file = fs::File::create(path).map_err(ExportError::IoError)?;
+        let mut out = io::BufWriter::new(file);
+        self.write(todolist, &mut out)?;
+        out.flush().map_err(ExportError::IoError)
+    }
+
+    /// Add to the end of an existing export file, whole-document formats refusing to
+    fn append(&self, _todolist: &TodoList, _path: &Path) -> Result<(), ExportError> {
+        Err(ExportError::AppendUnsupported)
+    }
 }
 
+#[derive(Debug)]
 pub enum ExportError {
     SerializationError(String),
     IoError(std::io::Error),
+    AppendUnsupported,
+    WouldOverwriteSave(PathBuf),
 }
 
-pub struct JsonExporter;
+fn open_append(path: &Path) -> Result<fs::File, ExportError> {
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(ExportError::IoError)
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExportError::SerializationError(msg) => write!(f, "Serialization failed {}", msg),
+            ExportError::IoError(e) => write!(f, "IO error {}", e),
+            ExportError::AppendUnsupported => write!(
+                f,
+                "Only CSV, Markdown and JSONL exports can be appended to, JSON and YAML are whole documents"
+            ),
+            ExportError::WouldOverwriteSave(path) => write!(
+                f,
+                "Refusing to export to {}, it is the save file (use --force to overwrite it)",
+                path.display()
+            ),
+        }
+    }
+}
+
+/// The file a chain of symlinks ends at, so a rename replaces the target and keeps the links
+fn resolve_symlinks(path: &Path) -> PathBuf {
+    let mut path = path.to_path_buf();
+    // As many links as Linux follows before giving up on a loop
+    for _ in 0..40 {
+        match fs::read_link(&path) {
+            Ok(target) => {
+                path = match path.parent() {
+                    Some(parent) => parent.join(target),
+                    None => target,
+                }
+            }
+            Err(_) => break,
+        }
+    }
+    path
+}
+
+pub struct JsonExporter {
+    /// What each nesting level is indented with
+    pub indent: String,
+}
+
+impl Default for JsonExporter {
+    fn default() -> Self {
+        JsonExporter {
+            indent: "  ".to_string(),
+        }
+    }
+}
 
 impl Exporter for JsonExporter {
-    fn export(&self, todolist: &TodoList) -> Result<(), ExportError> {
-        let json = serde_json::to_string_pretty(todolist)
-            .map_err(|e| ExportError::SerializationError(e.to_string()))?;
-        fs::write(&todolist.path, json).map_err(ExportError::IoError)?;
+    fn write(&self, todolist: &TodoList, out: &mut dyn Write) -> Result<(), ExportError> {
+        let formatter = serde_json::ser::PrettyFormatter::with_indent(self.indent.as_bytes());
+        let mut serializer = serde_json::Serializer::with_formatter(out, formatter);
+        todolist
+            .serialize(&mut serializer)
+            .map_err(|e| ExportError::SerializationError(e.to_string()))
+    }
+
+    fn export_to_path(&self, todolist: &TodoList, path: &Path) -> Result<(), ExportError> {
+        let mut json = vec![];
+        self.write(todolist, &mut json)?;
+        // Write next to the file then rename over it, so it is never left half-written
+        let path = resolve_symlinks(path);
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        fs::write(&tmp, json).map_err(ExportError::IoError)?;
+        fs::rename(&tmp, &path).map_err(ExportError::IoError)?;
         Ok(())
     }
 }
 
+/// Flat CSV row of a task, since CSV has no room for lists they are joined with `;`
+#[derive(Serialize, Deserialize)]
+pub struct CsvTask {
+    pub id: i32,
+    pub title: String,
+    pub done: bool,
+    pub created_at: DateTime<Local>,
+    pub completed_at: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub priority: Option<PriorityEnum>,
+    #[serde(default)]
+    pub due_at: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub recurrence: Option<RecurrenceEnum>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub started_at: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub total_seconds: i64,
+    #[serde(default)]
+    pub progress: u8,
+    #[serde(default)]
+    pub title_history: String,
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub remind_at: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub parent_id: Option<i32>,
+    #[serde(default)]
+    pub tags: String,
+    #[serde(default)]
+    pub status: StatusEnum,
+    #[serde(default)]
+    pub escalated_at: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub modified_at: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub links: String,
+    #[serde(default)]
+    pub lead_time_seconds: Option<i64>,
+    #[serde(default)]
+    pub hide_until: Option<DateTime<Local>>,
+}
+
+fn join_list(list: &[String]) -> String {
+    list.join(";")
+}
+
+fn split_list(list: &str) -> Vec<String> {
+    list.split(';')
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+impl From<&Task> for CsvTask {
+    fn from(task: &Task) -> Self {
+        CsvTask {
+            id: task.id,
+            title: task.title.clone(),
+            done: task.done,
+            created_at: task.created_at,
+            completed_at: task.completed_at,
+            priority: task.priority.clone(),
+            due_at: task.due_at,
+            recurrence: task.recurrence.clone(),
+            description: task.description.clone(),
+            started_at: task.started_at,
+            total_seconds: task.total_seconds,
+            progress: task.progress,
+            title_history: join_list(&task.title_history),
+            source: task.source.clone(),
+            remind_at: task.remind_at,
+            parent_id: task.parent_id,
+            tags: join_list(&task.tags),
+            status: task.status,
+            escalated_at: task.escalated_at,
+            modified_at: task.modified_at,
+            links: join_list(&task.links),
+            lead_time_seconds: task.lead_time_seconds,
+            hide_until: task.hide_until,
+        }
+    }
+}
+
+impl From<CsvTask> for Task {
+    fn from(row: CsvTask) -> Self {
+        Task {
+            id: row.id,
+            title: row.title,
+            done: row.done,
+            created_at: row.created_at,
+            completed_at: row.completed_at,
+            priority: row.priority,
+            due_at: row.due_at,
+            recurrence: row.recurrence,
+            description: row.description,
+            started_at: row.started_at,
+            total_seconds: row.total_seconds,
+            progress: row.progress,
+            title_history: split_list(&row.title_history),
+            source: row.source,
+            remind_at: row.remind_at,
+            parent_id: row.parent_id,
+            tags: split_list(&row.tags),
+            status: row.status,
+            escalated_at: row.escalated_at,
+            modified_at: row.modified_at,
+            links: split_list(&row.links),
+            lead_time_seconds: row.lead_time_seconds,
+            hide_until: row.hide_until,
+        }
+    }
+}
+
 pub struct CsvExporter;
 
-impl Exporter for CsvExporter {
-    fn export(&self, todolist: &TodoList) -> Result<(), ExportError> {
-        let mut csv = csv::Writer::from_path(todolist.path.with_extension("csv"))
-            .map_err(|e| ExportError::SerializationError(e.to_string()))?;
-        for task in todolist.tasks.iter() {
-            csv.serialize(task)
-                .map_err(|e| ExportError::SerializationError(e.to_string()))?;
+/// The CSV columns holding dates, shown as the timestamp setting says
+const DATE_COLUMNS: [&str; 8] = [
+    "created_at",
+    "completed_at",
+    "due_at",
+    "started_at",
+    "remind_at",
+    "escalated_at",
+    "modified_at",
+    "hide_until",
+];
+
+fn csv_error(e: impl fmt::Display) -> ExportError {
+    ExportError::SerializationError(e.to_string())
+}
+
+impl CsvExporter {
+    fn write_rows(todolist: &TodoList, out: impl Write, headers: bool) -> Result<(), ExportError> {
+        let mut csv = csv::WriterBuilder::new()
+            .has_headers(headers)
+            .from_writer(out);
+        if todolist.timestamps == TimestampEnum::Datetime {
+            for task in todolist.tasks.iter() {
+                csv.serialize(CsvTask::from(task)).map_err(csv_error)?;
+            }
+        } else {
+            Self::write_rows_reformatted(todolist, &mut csv, headers)?;
         }
         csv.flush().map_err(ExportError::IoError)?;
         Ok(())
     }
+
+    /// Write the rows in full to memory, then again with the dates reformatted or left out
+    fn write_rows_reformatted(
+        todolist: &TodoList,
+        csv: &mut csv::Writer<impl Write>,
+        headers: bool,
+    ) -> Result<(), ExportError> {
+        let mut full = csv::Writer::from_writer(vec![]);
+        for task in todolist.tasks.iter() {
+            full.serialize(CsvTask::from(task)).map_err(csv_error)?;
+        }
+        let full = full.into_inner().map_err(csv_error)?;
+        if full.is_empty() {
+            return Ok(());
+        }
+        let mut reader = csv::Reader::from_reader(full.as_slice());
+        let names = reader.headers().map_err(csv_error)?.clone();
+        let timestamps = todolist.timestamps;
+        // Each kept column, with whether it is a date and a due date
+        let columns: Vec<(usize, bool, bool)> = names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| (index, DATE_COLUMNS.contains(&name), name == "due_at"))
+            .filter(|&(_, date, due)| !date || due || timestamps != TimestampEnum::None)
+            .collect();
+        if headers {
+            csv.write_record(columns.iter().map(|&(index, _, _)| &names[index]))
+                .map_err(csv_error)?;
+        }
+        for record in reader.records() {
+            let record = record.map_err(csv_error)?;
+            let mut row = vec![];
+            for &(index, date, due) in columns.iter() {
+                let value = &record[index];
+                if !date || value.is_empty() {
+                    row.push(value.to_string());
+                    continue;
+                }
+                let value = DateTime::parse_from_rfc3339(value)
+                    .map_err(csv_error)?
+                    .with_timezone(&Local);
+                row.push(if due {
+                    timestamps.format_due(value)
+                } else {
+                    timestamps.format(value).unwrap_or_default()
+                });
+            }
+            csv.write_record(&row).map_err(csv_error)?;
+        }
+        Ok(())
+    }
+}
+
+impl Exporter for CsvExporter {
+    fn write(&self, todolist: &TodoList, out: &mut dyn Write) -> Result<(), ExportError> {
+        Self::write_rows(todolist, out, true)
+    }
+
+    /// Append rows, writing the header only when the file is new or empty
+    fn append(&self, todolist: &TodoList, path: &Path) -> Result<(), ExportError> {
+        let file = open_append(path)?;
+        let empty = file.metadata().map_err(ExportError::IoError)?.len() == 0;
+        Self::write_rows(todolist, file, empty)
+    }
 }
 
 pub struct YamlExporter;
 
 impl Exporter for YamlExporter {
-    fn export(&self, todolist: &TodoList) -> Result<(), ExportError> {
+    fn write(&self, todolist: &TodoList, out: &mut dyn Write) -> Result<(), ExportError> {
         let yaml = serde_yml::to_string(todolist)
             .map_err(|e| ExportError::SerializationError(e.to_string()))?;
-        fs::write(todolist.path.with_extension("yaml"), yaml).map_err(ExportError::IoError)?;
-        Ok(())
+        out.write_all(yaml.as_bytes()).map_err(ExportError::IoError)
     }
 }
 
 pub struct MarkdownExporter;
 
+impl MarkdownExporter {
+    fn push_task(markdown: &mut String, task: &Task, timestamps: TimestampEnum) {
+        markdown.push_str("- [");
+        markdown.push(if task.done { 'x' } else { ' ' });
+        markdown.push_str("] ");
+        match task.priority {
+            Some(PriorityEnum::High) => markdown.push_str("🔴 "),
+            Some(PriorityEnum::Medium) => markdown.push_str("🟡 "),
+            Some(PriorityEnum::Low) => markdown.push_str("🟢 "),
+            None => (),
+        }
+        markdown.push_str(&task.title);
+        if let Some(due) = task.due_at {
+            markdown.push_str(&format!(" (due {})", due.format("%Y-%m-%d")));
+        }
+        if let Some(created) = timestamps.format(task.created_at) {
+            markdown.push_str(&format!(" - Created at {}", created));
+        }
+        if let Some(completed) = task.completed_at.and_then(|dt| timestamps.format(dt)) {
+            markdown.push_str(&format!(" - Completed at {}", completed));
+        }
+        for link in task.links.iter() {
+            markdown.push_str(&format!(" - [{0}]({0})", link));
+        }
+        markdown.push('\n');
+    }
+}
+
+impl MarkdownExporter {
+    fn render(todolist: &TodoList) -> String {
+        let mut markdown = String::from("## Pending\n\n");
+        for task in todolist.pending_tasks() {
+            Self::push_task(&mut markdown, task, todolist.timestamps);
+        }
+        markdown.push_str("\n## Completed\n\n");
+        for task in todolist.completed_tasks() {
+            Self::push_task(&mut markdown, task, todolist.timestamps);
+        }
+        markdown
+    }
+}
+
 impl Exporter for MarkdownExporter {
-    fn export(&self, todolist: &TodoList) -> Result<(), ExportError> {
-        let mut markdown = String::new();
-        for task in &todolist.tasks {
-            markdown.push_str("- [");
-            if !task.done {
-                markdown.push('x');
-            }
-            markdown.push_str("] ");
-            markdown.push_str(&task.title);
-            markdown.push_str(&format!(" - Created at {}", task.created_at));
-            if let Some(completed) = task.completed_at {
-                markdown.push_str(&format!(" - Completed at {}", completed));
-            }
-            markdown.push('\n');
+    fn write(&self, todolist: &TodoList, out: &mut dyn Write) -> Result<(), ExportError> {
+        out.write_all(Self::render(todolist).as_bytes())
+            .map_err(ExportError::IoError)
+    }
+
+    /// Append the sections after a blank line
+    fn append(&self, todolist: &TodoList, path: &Path) -> Result<(), ExportError> {
+        let mut file = open_append(path)?;
+        let empty = file.metadata().map_err(ExportError::IoError)?.len() == 0;
+        let separator = if empty { "" } else { "\n" };
+        write!(file, "{}{}", separator, Self::render(todolist)).map_err(ExportError::IoError)
+    }
+}
+
+/// A GitHub task list: subtasks indented under their parent, high priorities in bold and
+/// completed tasks struck through, leaving out the dates that clutter issues
+pub struct GfmExporter;
+
+impl Exporter for GfmExporter {
+    fn write(&self, todolist: &TodoList, out: &mut dyn Write) -> Result<(), ExportError> {
+        let tasks: Vec<&Task> = todolist.tasks.iter().collect();
+        for (task, depth) in TodoList::nest_tasks(&tasks) {
+            let title = if task.done {
+                format!("~~{}~~", task.title)
+            } else if task.priority == Some(PriorityEnum::High) {
+                format!("**{}**", task.title)
+            } else {
+                task.title.clone()
+            };
+            writeln!(
+                out,
+                "{}- [{}] {}",
+                "  ".repeat(depth),
+                if task.done { 'x' } else { ' ' },
+                title
+            )
+            .map_err(ExportError::IoError)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct JsonlExporter;
+
+impl Exporter for JsonlExporter {
+    fn write(&self, todolist: &TodoList, out: &mut dyn Write) -> Result<(), ExportError> {
+        for task in todolist.tasks.iter() {
+            let line = serde_json::to_string(task)
+                .map_err(|e| ExportError::SerializationError(e.to_string()))?;
+            writeln!(out, "{}", line).map_err(ExportError::IoError)?;
         }
-        fs::write(todolist.path.with_extension("md"), markdown).map_err(ExportError::IoError)?;
         Ok(())
     }
+
+    fn append(&self, todolist: &TodoList, path: &Path) -> Result<(), ExportError> {
+        let mut out = io::BufWriter::new(open_append(path)?);
+        self.write(todolist, &mut out)?;
+        out.flush().map_err(ExportError::IoError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn sample() -> TodoList {
+        let mut todolist = TodoList::empty(Path::new("todo.json"));
+        let mut task = Task::new(1, "report".to_string(), Some(PriorityEnum::High));
+        task.created_at = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        task.tags = vec!["work".to_string(), "q2".to_string()];
+        task.links = vec!["https://example.com/q2".to_string()];
+        todolist.tasks.push(task);
+        let mut task = Task::new(2, "groceries".to_string(), None);
+        task.created_at = Local.with_ymd_and_hms(2024, 6, 2, 12, 0, 0).unwrap();
+        task.complete(Local.with_ymd_and_hms(2024, 6, 3, 12, 0, 0).unwrap());
+        todolist.tasks.push(task);
+        todolist
+    }
+
+    fn write(exporter: &dyn Exporter) -> String {
+        let mut out = vec![];
+        exporter.write(&sample(), &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_json_to_buffer() {
+        let json = write(&JsonExporter::default());
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["tasks"][0]["title"], "report");
+        assert_eq!(value["tasks"][1]["done"], true);
+        assert!(json.starts_with("{\n  \"tasks\": ["));
+    }
+
+    #[test]
+    fn test_csv_to_buffer() {
+        let csv = write(&CsvExporter);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("id,title,done,"));
+        assert!(lines[1].starts_with("1,report,false,"));
+        assert!(lines[1].contains(",work;q2,"));
+        assert!(lines[1].contains(",https://example.com/q2,"));
+        assert!(lines[2].starts_with("2,groceries,true,"));
+    }
+
+    #[test]
+    fn test_yaml_to_buffer() {
+        let yaml = write(&YamlExporter);
+        let value: serde_yml::Value = serde_yml::from_str(&yaml).unwrap();
+        assert_eq!(value["tasks"][0]["title"].as_str(), Some("report"));
+        assert_eq!(value["tasks"][1]["done"].as_bool(), Some(true));
+    }
+
+    #[test]
+    fn test_markdown_to_buffer() {
+        let markdown = write(&MarkdownExporter);
+        let lines: Vec<&str> = markdown.lines().collect();
+        assert_eq!(lines[0], "## Pending");
+        assert!(lines[2].starts_with("- [ ] 🔴 report - Created at 2024-06-01"));
+        assert!(lines[2].ends_with(" - [https://example.com/q2](https://example.com/q2)"));
+        assert_eq!(lines[4], "## Completed");
+        assert!(lines[6].starts_with("- [x] groceries - Created at 2024-06-02"));
+    }
+
+    #[test]
+    fn test_gfm_nests_subtasks_without_dates() {
+        let mut todolist = sample();
+        let mut task = Task::new(3, "print slides".to_string(), Some(PriorityEnum::High));
+        task.parent_id = Some(1);
+        todolist.tasks.push(task);
+        let mut task = Task::new(4, "book room".to_string(), None);
+        task.parent_id = Some(3);
+        todolist.tasks.push(task);
+
+        let mut out = vec![];
+        GfmExporter.write(&todolist, &mut out).unwrap();
+        let gfm = String::from_utf8(out).unwrap();
+        assert_eq!(
+            gfm,
+            "- [ ] **report**\n  - [ ] **print slides**\n    - [ ] book room\n- [x] ~~groceries~~\n"
+        );
+        assert!(!gfm.contains("2024"));
+        assert!(!gfm.contains("Created"));
+    }
+
+    #[test]
+    fn test_csv_and_markdown_timestamps() {
+        let mut todolist = sample();
+        todolist.tasks[0].due_at = Some(Local.with_ymd_and_hms(2024, 6, 7, 17, 0, 0).unwrap());
+        let write = |exporter: &dyn Exporter, todolist: &TodoList| {
+            let mut out = vec![];
+            exporter.write(todolist, &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        todolist.timestamps = TimestampEnum::Date;
+        let csv = write(&CsvExporter, &todolist);
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        let headers = reader.headers().unwrap().clone();
+        let rows: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+        let column = |name: &str| headers.iter().position(|header| header == name).unwrap();
+        assert_eq!(&rows[0][column("created_at")], "2024-06-01");
+        assert_eq!(&rows[0][column("due_at")], "2024-06-07");
+        assert_eq!(&rows[0][column("completed_at")], "");
+        assert_eq!(&rows[1][column("completed_at")], "2024-06-03");
+        assert_eq!(&rows[1][column("title")], "groceries");
+        let markdown = write(&MarkdownExporter, &todolist);
+        assert!(markdown.contains("- [ ] 🔴 report (due 2024-06-07) - Created at 2024-06-01 - ["));
+        assert!(
+            markdown
+                .contains("- [x] groceries - Created at 2024-06-02 - Completed at 2024-06-03\n")
+        );
+
+        todolist.timestamps = TimestampEnum::None;
+        let csv = write(&CsvExporter, &todolist);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert!(lines[0].starts_with("id,title,done,priority,due_at,recurrence,"));
+        assert!(!lines[0].contains("created_at"));
+        assert!(!lines[0].contains("modified_at"));
+        assert!(lines[1].starts_with("1,report,false,High,2024-06-07,"));
+        assert!(!csv.contains("2024-06-01"));
+        let markdown = write(&MarkdownExporter, &todolist);
+        assert!(!markdown.contains("Created at"));
+        assert!(!markdown.contains("Completed at"));
+        assert!(markdown.contains("- [x] groceries\n"));
+        assert!(markdown.contains("report (due 2024-06-07) - ["));
+    }
+
+    #[test]
+    fn test_jsonl_to_buffer() {
+        let jsonl = write(&JsonlExporter);
+        let tasks: Vec<Task> = jsonl
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].tags, vec!["work", "q2"]);
+        assert!(tasks[1].done);
+    }
 }
@@ -0,0 +1,346 @@
+use chrono::{DateTime, Local, NaiveDate};
+
+use crate::task::{PriorityEnum, StatusEnum, Task};
+
+/// A set of conditions a task must all meet, built by chaining
+///
+/// ```
+/// use todo_cli::{filter::TaskFilter, task::PriorityEnum};
+///
+/// let filter = TaskFilter::new().pending().priority(PriorityEnum::High).tag("work");
+/// ```
+#[derive(Clone, Default, Debug)]
+pub struct TaskFilter {
+    done: Option<bool>,
+    status: Option<StatusEnum>,
+    priority: Option<PriorityEnum>,
+    tags: Vec<String>,
+    excluded_tags: Vec<String>,
+    source: Option<String>,
+    has_due: Option<bool>,
+    due_before: Option<DateTime<Local>>,
+    due_between: Option<(DateTime<Local>, DateTime<Local>)>,
+    completed_between: Option<(NaiveDate, NaiveDate)>,
+    modified_on: Option<NaiveDate>,
+    visible_at: Option<DateTime<Local>>,
+    after_id: Option<i32>,
+    before_id: Option<i32>,
+}
+
+impl TaskFilter {
+    pub fn new() -> Self {
+        TaskFilter::default()
+    }
+
+    pub fn pending(mut self) -> Self {
+        self.done = Some(false);
+        self
+    }
+
+    pub fn completed(mut self) -> Self {
+        self.done = Some(true);
+        self
+    }
+
+    pub fn status(mut self, status: StatusEnum) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    pub fn priority(mut self, priority: PriorityEnum) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Require this tag, chaining requires every tag
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tags.push(tag.to_string());
+        self
+    }
+
+    /// Reject tasks with this tag, even when they have every required tag
+    pub fn exclude_tag(mut self, tag: &str) -> Self {
+        self.excluded_tags.push(tag.to_string());
+        self
+    }
+
+    pub fn source(mut self, source: &str) -> Self {
+        self.source = Some(source.to_string());
+        self
+    }
+
+    /// With a due date
+    pub fn has_due(mut self) -> Self {
+        self.has_due = Some(true);
+        self
+    }
+
+    /// Without a due date
+    pub fn no_due(mut self) -> Self {
+        self.has_due = Some(false);
+        self
+    }
+
+    /// Due strictly before `date`
+    pub fn due_before(mut self, date: DateTime<Local>) -> Self {
+        self.due_before = Some(date);
+        self
+    }
+
+    /// Due from `from` to `to`, both included
+    pub fn due_between(mut self, from: DateTime<Local>, to: DateTime<Local>) -> Self {
+        self.due_between = Some((from, to));
+        self
+    }
+
+    /// Completed on a day from `start` to `end`, both included
+    pub fn completed_between(mut self, start: NaiveDate, end: NaiveDate) -> Self {
+        self.completed_between = Some((start, end));
+        self
+    }
+
+    /// Last changed on `date`
+    pub fn modified_on(mut self, date: NaiveDate) -> Self {
+        self.modified_on = Some(date);
+        self
+    }
+
+    /// Not deferred past `now`
+    pub fn visible_at(mut self, now: DateTime<Local>) -> Self {
+        self.visible_at = Some(now);
+        self
+    }
+
+    /// With an id strictly greater than `id`
+    pub fn after_id(mut self, id: i32) -> Self {
+        self.after_id = Some(id);
+        self
+    }
+
+    /// With an id strictly less than `id`
+    pub fn before_id(mut self, id: i32) -> Self {
+        self.before_id = Some(id);
+        self
+    }
+
+    pub fn matches(&self, task: &Task) -> bool {
+        self.done.is_none_or(|done| task.done == done)
+            && self.status.is_none_or(|status| task.status == status)
+            && self
+                .priority
+                .as_ref()
+                .is_none_or(|priority| task.priority.as_ref() == Some(priority))
+            && self.tags.iter().all(|tag| task.tags.contains(tag))
+            && !self.excluded_tags.iter().any(|tag| task.tags.contains(tag))
+            && self
+                .source
+                .as_ref()
+                .is_none_or(|source| task.source.as_ref() == Some(source))
+            && self
+                .has_due
+                .is_none_or(|has_due| task.due_at.is_some() == has_due)
+            && self
+                .due_before
+                .is_none_or(|date| task.due_at.is_some_and(|due| due < date))
+            && self
+                .due_between
+                .is_none_or(|(from, to)| task.due_at.is_some_and(|due| from <= due && due <= to))
+            && self.completed_between.is_none_or(|(start, end)| {
+                task.completed_at
+                    .is_some_and(|completed| (start..=end).contains(&completed.date_naive()))
+            })
+            && self.modified_on.is_none_or(|date| {
+                task.modified_at
+                    .is_some_and(|modified| modified.date_naive() == date)
+            })
+            && self.visible_at.is_none_or(|now| !task.is_deferred(now))
+            && self.after_id.is_none_or(|id| task.id > id)
+            && self.before_id.is_none_or(|id| task.id < id)
+    }
+
+    /// The filter as a closure, for `Iterator::filter` and the like
+    pub fn predicate(self) -> impl Fn(&Task) -> bool {
+        move |task| self.matches(task)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn sample() -> Vec<Task> {
+        let mut tasks = vec![
+            Task::new(1, "report".to_string(), Some(PriorityEnum::High)),
+            Task::new(2, "groceries".to_string(), Some(PriorityEnum::Low)),
+            Task::new(3, "deploy".to_string(), Some(PriorityEnum::High)),
+            Task::new(4, "call mum".to_string(), None),
+        ];
+        tasks[0].tags = vec!["work".to_string()];
+        tasks[0].due_at = Some(Local.with_ymd_and_hms(2024, 6, 3, 12, 0, 0).unwrap());
+        tasks[1].tags = vec!["home".to_string()];
+        tasks[2].tags = vec!["work".to_string(), "ops".to_string()];
+        tasks[2].due_at = Some(Local.with_ymd_and_hms(2024, 6, 20, 12, 0, 0).unwrap());
+        tasks[2].complete(Local.with_ymd_and_hms(2024, 6, 5, 9, 0, 0).unwrap());
+        tasks
+    }
+
+    fn ids(tasks: &[Task], filter: TaskFilter) -> Vec<i32> {
+        tasks
+            .iter()
+            .filter(|task| filter.matches(task))
+            .map(|task| task.id)
+            .collect()
+    }
+
+    #[test]
+    fn test_visible_at_hides_deferred_tasks() {
+        let mut tasks = sample();
+        tasks[1].hide_until = Some(Local.with_ymd_and_hms(2024, 6, 10, 0, 0, 0).unwrap());
+        let before = Local.with_ymd_and_hms(2024, 6, 9, 23, 0, 0).unwrap();
+        let after = Local.with_ymd_and_hms(2024, 6, 10, 0, 0, 0).unwrap();
+        assert_eq!(
+            ids(&tasks, TaskFilter::new().visible_at(before)),
+            vec![1, 3, 4]
+        );
+        assert_eq!(
+            ids(&tasks, TaskFilter::new().visible_at(after)),
+            vec![1, 2, 3, 4]
+        );
+        assert_eq!(ids(&tasks, TaskFilter::new()), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_id_bounds() {
+        let tasks = sample();
+        assert_eq!(ids(&tasks, TaskFilter::new().after_id(2)), vec![3, 4]);
+        assert_eq!(ids(&tasks, TaskFilter::new().before_id(3)), vec![1, 2]);
+        assert_eq!(
+            ids(&tasks, TaskFilter::new().after_id(1).before_id(4)),
+            vec![2, 3]
+        );
+        assert_eq!(
+            ids(&tasks, TaskFilter::new().after_id(1).before_id(4).pending()),
+            vec![2]
+        );
+        assert!(ids(&tasks, TaskFilter::new().after_id(3).before_id(3)).is_empty());
+    }
+
+    #[test]
+    fn test_empty_filter_matches_all() {
+        assert_eq!(ids(&sample(), TaskFilter::new()), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_status_priority_and_tags() {
+        let tasks = sample();
+        assert_eq!(ids(&tasks, TaskFilter::new().pending()), vec![1, 2, 4]);
+        assert_eq!(ids(&tasks, TaskFilter::new().completed()), vec![3]);
+        assert_eq!(
+            ids(&tasks, TaskFilter::new().priority(PriorityEnum::High)),
+            vec![1, 3]
+        );
+        assert_eq!(
+            ids(
+                &tasks,
+                TaskFilter::new().pending().priority(PriorityEnum::High)
+            ),
+            vec![1]
+        );
+        assert_eq!(ids(&tasks, TaskFilter::new().tag("work")), vec![1, 3]);
+        assert_eq!(
+            ids(&tasks, TaskFilter::new().tag("work").tag("ops")),
+            vec![3]
+        );
+    }
+
+    #[test]
+    fn test_dates() {
+        let tasks = sample();
+        let cutoff = Local.with_ymd_and_hms(2024, 6, 10, 0, 0, 0).unwrap();
+        assert_eq!(ids(&tasks, TaskFilter::new().due_before(cutoff)), vec![1]);
+        let from = Local.with_ymd_and_hms(2024, 6, 3, 12, 0, 0).unwrap();
+        let to = Local.with_ymd_and_hms(2024, 6, 20, 12, 0, 0).unwrap();
+        assert_eq!(
+            ids(&tasks, TaskFilter::new().due_between(from, to)),
+            vec![1, 3]
+        );
+        let day = NaiveDate::from_ymd_opt(2024, 6, 5).unwrap();
+        assert_eq!(
+            ids(&tasks, TaskFilter::new().completed_between(day, day)),
+            vec![3]
+        );
+    }
+
+    #[test]
+    fn test_exclude_tag() {
+        let tasks = sample();
+        assert_eq!(
+            ids(&tasks, TaskFilter::new().exclude_tag("work")),
+            vec![2, 4]
+        );
+        assert_eq!(
+            ids(
+                &tasks,
+                TaskFilter::new().exclude_tag("ops").exclude_tag("home")
+            ),
+            vec![1, 4]
+        );
+        assert_eq!(
+            ids(&tasks, TaskFilter::new().tag("work").exclude_tag("ops")),
+            vec![1]
+        );
+        assert!(ids(&tasks, TaskFilter::new().tag("work").exclude_tag("work")).is_empty());
+    }
+
+    #[test]
+    fn test_has_due() {
+        let tasks = sample();
+        assert_eq!(ids(&tasks, TaskFilter::new().has_due()), vec![1, 3]);
+        assert_eq!(ids(&tasks, TaskFilter::new().no_due()), vec![2, 4]);
+        assert_eq!(ids(&tasks, TaskFilter::new().has_due().pending()), vec![1]);
+        assert_eq!(
+            ids(
+                &tasks,
+                TaskFilter::new().no_due().priority(PriorityEnum::Low)
+            ),
+            vec![2]
+        );
+    }
+
+    #[test]
+    fn test_predicate() {
+        let tasks = sample();
+        let predicate = TaskFilter::new().pending().tag("home").predicate();
+        let titles: Vec<_> = tasks
+            .iter()
+            .filter(|task| predicate(task))
+            .map(|task| task.title.as_str())
+            .collect();
+        assert_eq!(titles, vec!["groceries"]);
+    }
+
+    #[test]
+    fn test_status() {
+        let mut tasks = sample();
+        tasks[1].set_status(StatusEnum::Waiting, Local::now());
+        tasks[3].set_status(StatusEnum::Doing, Local::now());
+        assert_eq!(
+            ids(&tasks, TaskFilter::new().status(StatusEnum::Waiting)),
+            vec![2]
+        );
+        assert_eq!(
+            ids(&tasks, TaskFilter::new().status(StatusEnum::Doing)),
+            vec![4]
+        );
+        assert_eq!(
+            ids(&tasks, TaskFilter::new().status(StatusEnum::Todo)),
+            vec![1]
+        );
+        assert_eq!(
+            ids(&tasks, TaskFilter::new().status(StatusEnum::Done)),
+            vec![3]
+        );
+        assert_eq!(ids(&tasks, TaskFilter::new().pending()), vec![1, 2, 4]);
+    }
+}
@@ -0,0 +1,305 @@
+use chrono::{DateTime, Local, NaiveDate};
+use std::{fmt, fs, path::Path};
+
+use crate::{
+    cli::end_of_day,
+    exporter::{CsvTask, ExportError, FormatEnum},
+    store::MemoryStore,
+    task::{PriorityEnum, Task},
+    todolist::TodoList,
+};
+
+#[derive(Debug)]
+pub enum ImportError {
+    DeserializationError(String),
+    IoError(std::io::Error),
+    UnsupportedFormat(FormatEnum),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImportError::DeserializationError(msg) => write!(f, "Deserialization failed {}", msg),
+            ImportError::IoError(e) => write!(f, "IO error {}", e),
+            ImportError::UnsupportedFormat(format) => {
+                write!(f, "Importing from {:?} is not supported", format)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RoundtripError {
+    Export(ExportError),
+    Import(ImportError),
+}
+
+impl fmt::Display for RoundtripError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RoundtripError::Export(e) => write!(f, "{}", e),
+            RoundtripError::Import(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+fn deserialization_error(e: impl fmt::Display) -> ImportError {
+    ImportError::DeserializationError(e.to_string())
+}
+
+/// A list holding `tasks`, kept in memory
+fn memory_list(path: &Path, tasks: Vec<Task>) -> TodoList {
+    let mut todolist = TodoList::empty(path);
+    todolist.store = Box::<MemoryStore>::default();
+    todolist.tasks = tasks;
+    todolist
+}
+
+pub fn import(path: &Path, format: FormatEnum) -> Result<Vec<Task>, ImportError> {
+    let content = fs::read_to_string(path).map_err(ImportError::IoError)?;
+    parse(&content, format, path).map(|todolist| todolist.tasks)
+}
+
+/// Read an export back, the list name and description only surviving JSON and YAML
+pub fn parse(content: &str, format: FormatEnum, path: &Path) -> Result<TodoList, ImportError> {
+    let mut todolist = match format {
+        FormatEnum::Json => serde_json::from_str(content).map_err(deserialization_error)?,
+        FormatEnum::Yaml => serde_yml::from_str(content).map_err(deserialization_error)?,
+        FormatEnum::Csv => {
+            let tasks = csv::Reader::from_reader(content.as_bytes())
+                .deserialize()
+                .map(|row| row.map(|row: CsvTask| Task::from(row)))
+                .collect::<Result<Vec<Task>, _>>()
+                .map_err(deserialization_error)?;
+            memory_list(path, tasks)
+        }
+        FormatEnum::Jsonl => {
+            let tasks = content
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(serde_json::from_str)
+                .collect::<Result<Vec<Task>, _>>()
+                .map_err(deserialization_error)?;
+            memory_list(path, tasks)
+        }
+        FormatEnum::Markdown => memory_list(path, parse_markdown(content)?),
+        format => return Err(ImportError::UnsupportedFormat(format)),
+    };
+    todolist.path = path.to_path_buf();
+    Ok(todolist)
+}
+
+/// A date as the Markdown export writes it, `DateTime<Local>`'s display form
+fn parse_markdown_date(value: &str) -> Result<DateTime<Local>, ImportError> {
+    DateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f %:z")
+        .map(|date| date.with_timezone(&Local))
+        .map_err(|e| deserialization_error(format!("invalid date '{}' {}", value, e)))
+}
+
+/// Read the checklist lines of a Markdown export, numbering the tasks in order
+///
+/// Only the status, priority, title, due day, creation and completion dates and links are
+/// written, so the ids, descriptions, tags, due times and every other field are lost.
+fn parse_markdown(content: &str) -> Result<Vec<Task>, ImportError> {
+    let mut tasks = vec![];
+    for line in content.lines() {
+        let (done, rest) = if let Some(rest) = line.strip_prefix("- [x] ") {
+            (true, rest)
+        } else if let Some(rest) = line.strip_prefix("- [ ] ") {
+            (false, rest)
+        } else {
+            continue;
+        };
+        let (priority, rest) = if let Some(rest) = rest.strip_prefix("🔴 ") {
+            (Some(PriorityEnum::High), rest)
+        } else if let Some(rest) = rest.strip_prefix("🟡 ") {
+            (Some(PriorityEnum::Medium), rest)
+        } else if let Some(rest) = rest.strip_prefix("🟢 ") {
+            (Some(PriorityEnum::Low), rest)
+        } else {
+            (None, rest)
+        };
+        let Some((title, dates)) = rest.rsplit_once(" - Created at ") else {
+            return Err(deserialization_error(format!(
+                "no creation date in '{}'",
+                line
+            )));
+        };
+        let (title, due) = match title
+            .strip_suffix(')')
+            .and_then(|title| title.rsplit_once(" (due "))
+        {
+            Some((title, day)) => {
+                let day =
+                    NaiveDate::parse_from_str(day, "%Y-%m-%d").map_err(deserialization_error)?;
+                (title, end_of_day(day))
+            }
+            None => (title, None),
+        };
+
+        let mut parts = dates.split(" - ");
+        let created_at = parse_markdown_date(parts.next().unwrap_or_default())?;
+        let mut task = Task::new(tasks.len() as i32 + 1, title.to_string(), priority);
+        task.created_at = created_at;
+        task.modified_at = Some(created_at);
+        task.due_at = due;
+        let mut completed_at = None;
+        for part in parts {
+            if let Some(completed) = part.strip_prefix("Completed at ") {
+                completed_at = Some(parse_markdown_date(completed)?);
+            } else if let Some((_, link)) = part
+                .strip_suffix(')')
+                .and_then(|link| link.split_once("]("))
+            {
+                task.links.push(link.to_string());
+            }
+        }
+        if done {
+            task.complete(completed_at.unwrap_or(created_at));
+        }
+        tasks.push(task);
+    }
+    Ok(tasks)
+}
+
+/// Write the list in `format` and read it back, to check what the format keeps
+///
+/// JSON and YAML keep every task field and the list name and description. JSONL keeps every
+/// task field and CSV too, as long as no tag, link or previous title holds a `;`, but both drop
+/// the name and description. Markdown drops most fields, see `parse_markdown`, and lists the
+/// pending tasks before the completed ones. GFM cannot be read back.
+pub fn roundtrip(todolist: &TodoList, format: FormatEnum) -> Result<TodoList, RoundtripError> {
+    let mut out = vec![];
+    format
+        .exporter()
+        .write(todolist, &mut out)
+        .map_err(RoundtripError::Export)?;
+    let content =
+        String::from_utf8(out).map_err(|e| RoundtripError::Import(deserialization_error(e)))?;
+    parse(&content, format, &todolist.path).map_err(RoundtripError::Import)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::{RecurrenceEnum, StatusEnum};
+    use chrono::TimeZone;
+
+    fn sample() -> TodoList {
+        let mut todolist = memory_list(Path::new("todo.json"), vec![]);
+        todolist.name = Some("work".to_string());
+        todolist.description = Some("Q2 goals".to_string());
+        let created = Local.with_ymd_and_hms(2024, 6, 1, 9, 30, 15).unwrap()
+            + chrono::Duration::nanoseconds(123_456_789);
+
+        let mut task = Task::new(1, "report".to_string(), Some(PriorityEnum::High));
+        task.created_at = created;
+        task.modified_at = Some(created + chrono::Duration::hours(1));
+        task.due_at = Some(Local.with_ymd_and_hms(2024, 6, 7, 17, 0, 0).unwrap());
+        task.recurrence = Some(RecurrenceEnum::Weekly);
+        task.description = Some("the quarterly one".to_string());
+        task.total_seconds = 90;
+        task.progress = 40;
+        task.title_history = vec!["draft".to_string()];
+        task.source = Some("laptop".to_string());
+        task.remind_at = Some(created + chrono::Duration::days(2));
+        task.tags = vec!["work".to_string(), "q2".to_string()];
+        task.status = StatusEnum::Doing;
+        task.escalated_at = Some(created + chrono::Duration::days(1));
+        task.links = vec!["https://example.com/q2".to_string()];
+        todolist.tasks.push(task);
+
+        let mut task = Task::new(2, "groceries".to_string(), None);
+        task.created_at = created;
+        task.parent_id = Some(1);
+        task.complete(created + chrono::Duration::minutes(45));
+        todolist.tasks.push(task);
+
+        let mut task = Task::new(3, "call bob".to_string(), Some(PriorityEnum::Low));
+        task.created_at = created;
+        task.modified_at = Some(created);
+        todolist.tasks.push(task);
+        todolist
+    }
+
+    #[test]
+    fn test_json_and_yaml_roundtrips_are_lossless() {
+        let todolist = sample();
+        for format in [FormatEnum::Json, FormatEnum::Yaml] {
+            let read = roundtrip(&todolist, format.clone()).unwrap();
+            assert_eq!(read.tasks, todolist.tasks, "{:?}", format);
+            assert_eq!(read.name, todolist.name);
+            assert_eq!(read.description, todolist.description);
+        }
+    }
+
+    #[test]
+    fn test_csv_and_jsonl_roundtrips_keep_the_tasks() {
+        let todolist = sample();
+        for format in [FormatEnum::Csv, FormatEnum::Jsonl] {
+            let read = roundtrip(&todolist, format.clone()).unwrap();
+            assert_eq!(read.tasks, todolist.tasks, "{:?}", format);
+            assert_eq!(read.name, None);
+        }
+    }
+
+    #[test]
+    fn test_markdown_roundtrip_drops_fields() {
+        let todolist = sample();
+        let read = roundtrip(&todolist, FormatEnum::Markdown).unwrap();
+        let titles: Vec<&str> = read.tasks.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, vec!["report", "call bob", "groceries"]);
+
+        // Kept: title, priority, done, creation and completion dates, links and due day
+        let report = &read.tasks[0];
+        let original = &todolist.tasks[0];
+        assert_eq!(report.priority, original.priority);
+        assert_eq!(report.created_at, original.created_at);
+        assert_eq!(report.links, original.links);
+        assert_eq!(
+            report.due_at.map(|due| due.date_naive()),
+            original.due_at.map(|due| due.date_naive())
+        );
+        let groceries = &read.tasks[2];
+        assert!(groceries.done);
+        assert_eq!(groceries.completed_at, todolist.tasks[1].completed_at);
+
+        // Dropped: ids, due times, status beyond done, and every other field
+        assert_eq!(groceries.id, 3);
+        assert_ne!(report.due_at, original.due_at);
+        assert_eq!(report.status, StatusEnum::Todo);
+        assert_eq!(report.description, None);
+        assert_eq!(report.recurrence, None);
+        assert!(report.tags.is_empty());
+        assert!(report.title_history.is_empty());
+        assert_eq!(report.progress, 0);
+        assert_eq!(report.total_seconds, 0);
+        assert_eq!(report.source, None);
+        assert_eq!(report.remind_at, None);
+        assert_eq!(report.escalated_at, None);
+        assert_eq!(groceries.parent_id, None);
+        assert_eq!(read.name, None);
+    }
+
+    #[test]
+    fn test_import_markdown_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tasks.md");
+        fs::write(
+            &path,
+            "## Pending\n\n- [ ] 🟡 write tests (due 2024-06-03) - Created at 2024-06-01 12:00:00 +00:00\n\n## Completed\n\n",
+        )
+        .unwrap();
+        let tasks = import(&path, FormatEnum::Markdown).unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].title, "write tests");
+        assert_eq!(tasks[0].priority, Some(PriorityEnum::Medium));
+        assert_eq!(
+            tasks[0].created_at,
+            chrono::Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap()
+        );
+
+        fs::write(&path, "- [ ] no dates\n").unwrap();
+        assert!(import(&path, FormatEnum::Markdown).is_err());
+    }
+}
@@ -0,0 +1,44 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+/// Tracks Ctrl-C so that a save in progress finishes before the process exits
+#[derive(Default, Debug)]
+pub struct Interrupt {
+    interrupted: AtomicBool,
+    saving: AtomicBool,
+}
+
+impl Interrupt {
+    pub fn install(self: &Arc<Self>) -> Result<(), ctrlc::Error> {
+        let interrupt = Arc::clone(self);
+        ctrlc::set_handler(move || {
+            if interrupt.handle() {
+                std::process::exit(130);
+            }
+        })
+    }
+
+    /// Record the interruption, returning whether it is safe to exit right away
+    pub fn handle(&self) -> bool {
+        self.interrupted.store(true, Ordering::SeqCst);
+        !self.saving.load(Ordering::SeqCst)
+    }
+
+    pub fn interrupted(&self) -> bool {
+        self.interrupted.load(Ordering::SeqCst)
+    }
+
+    pub fn begin_save(&self) {
+        self.saving.store(true, Ordering::SeqCst);
+    }
+
+    /// Mark the save as done, exiting if an interruption arrived meanwhile
+    pub fn end_save(&self) {
+        self.saving.store(false, Ordering::SeqCst);
+        if self.interrupted() {
+            std::process::exit(130);
+        }
+    }
+}
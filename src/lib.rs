@@ -0,0 +1,15 @@
+pub mod cli;
+pub mod color;
+pub mod config;
+pub mod duration;
+pub mod exporter;
+pub mod filter;
+pub mod importer;
+pub mod interrupt;
+pub mod prompt;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod stats;
+pub mod store;
+pub mod task;
+pub mod todolist;
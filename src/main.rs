@@ -1,46 +1,478 @@
-mod cli;
-mod exporter;
-mod task;
-mod todolist;
-
+use chrono::Local;
 use clap::Parser;
+use std::{
+    io::{self, IsTerminal},
+    path::Path,
+    process,
+};
+use terminal_size::{Width, terminal_size};
 
-use crate::{
-    cli::{Cli, Commands},
-    todolist::TodoList,
+use todo_cli::{
+    cli::{Cli, Commands, effective_width, title_lines},
+    config::{Config, ListFilterEnum},
+    exporter::ExportOptions,
+    filter::TaskFilter,
+    store::BackendEnum,
+    task::{TaskBuilder, hostname},
+    todolist::{OutputEnum, TodoList},
 };
 
+#[cfg(feature = "sqlite")]
+fn load_sqlite(path: &Path) -> TodoList {
+    let store = todo_cli::sqlite::SqliteStore::open(path).unwrap_or_else(|e| {
+        eprintln!("SQLite error {}", e);
+        process::exit(1);
+    });
+    TodoList::open(path, Box::new(store)).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    })
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn load_sqlite(_path: &Path) -> TodoList {
+    eprintln!("SQLite storage needs a build with the sqlite feature");
+    process::exit(1);
+}
+
 fn main() {
     let cli = Cli::parse();
-    let mut todolist = TodoList::load_tasks(cli.path);
+    // Checked before loading the list, so validating never writes anything
+    if let Commands::Validate { file } = &cli.command {
+        match TodoList::validate(file) {
+            Ok(problems) if problems.is_empty() => println!("{} is valid", file.display()),
+            Ok(problems) => {
+                for problem in problems {
+                    eprintln!("{}", problem);
+                }
+                process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+    let config = Config::load(&cli.config).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+    let backend = cli
+        .backend
+        .unwrap_or_else(|| BackendEnum::for_path(&cli.path));
+    let mut todolist = match backend {
+        BackendEnum::Sqlite => load_sqlite(&cli.path),
+        BackendEnum::Json if cli.strict => {
+            TodoList::load_tasks_strict(cli.path).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            })
+        }
+        BackendEnum::Json => TodoList::load_tasks(cli.path),
+    };
+    todolist.file_mode = config.file_mode;
+    todolist.max_completed = config.max_completed;
+    todolist.archive_pruned = config.archive_pruned;
+    todolist.journal = config.journal;
+    todolist.timestamps = config.timestamps;
+    todolist.theme = config.theme.clone();
+    if config.auto_archive_days > 0 {
+        let archived = todolist.auto_archive(config.auto_archive_days, Local::now());
+        if archived > 0 {
+            eprintln!("Archived {} tasks", archived);
+        }
+    }
+    if config.auto_escalate_days > 0 {
+        let escalated = todolist.auto_escalate(config.auto_escalate_days, Local::now());
+        if escalated > 0 {
+            eprintln!("Escalated {} tasks", escalated);
+        }
+    }
+    if let Err(e) = todolist.interrupt.install() {
+        eprintln!("Could not install the Ctrl-C handler {}", e);
+    }
+    if !cli.no_reminders {
+        for reminder in todolist.reminders(Local::now()) {
+            eprintln!("{}", reminder);
+        }
+    }
+    let is_terminal = io::stdout().is_terminal();
+    todolist.color = cli.color.enabled(is_terminal);
+    todolist.width = effective_width(
+        is_terminal,
+        terminal_size().map(|(Width(width), _)| width as usize),
+    );
     match cli.command {
-        Commands::Add { title, priority } => {
-            todolist.add_task(title, priority);
+        Commands::Add {
+            title,
+            split_lines,
+            priority,
+            due,
+            every,
+            description,
+            source,
+            remind,
+            parent,
+            tags,
+            links,
+            silent,
+        } => {
+            if let Some(parent) = parent
+                && !todolist.tasks.iter().any(|task| task.id == parent)
+            {
+                eprintln!("No task with id {}", parent);
+                process::exit(1);
+            }
+            let titles = if split_lines {
+                title_lines(&title)
+            } else if title.contains(['\n', '\r']) {
+                eprintln!(
+                    "The title spans several lines, use --split-lines to add one task per line"
+                );
+                process::exit(1);
+            } else {
+                vec![title]
+            };
+            let due = due.or_else(|| config.default_due(priority.as_ref(), Local::now()));
+            let builder = TaskBuilder::new()
+                .priority(priority)
+                .due(due)
+                .recurrence(every)
+                .description(description)
+                .source(source.or_else(hostname))
+                .remind(remind)
+                .parent(parent);
+            let builder = tags.into_iter().fold(builder, TaskBuilder::tag);
+            let builder = links.into_iter().fold(builder, TaskBuilder::link);
+            todolist.add_all(titles.into_iter().map(|title| builder.clone().title(title)));
+            if !silent {
+                todolist.list_tasks();
+            }
+        }
+        Commands::Split { id, titles } => {
+            todolist.split_task(id, titles);
             todolist.list_tasks();
         }
-        Commands::Remove { id } => {
-            todolist.remove_task(id);
+        Commands::Edit { id, title } => {
+            todolist.edit_task(id, title);
             todolist.list_tasks();
         }
-        Commands::Complete { id } => {
-            todolist.complete_task(id);
+        Commands::Defer { id, until } => {
+            todolist.defer_task(id, until);
+            todolist.list_tasks();
+        }
+        Commands::Touch { id } => {
+            todolist.touch_task(id);
+            todolist.list_tasks();
+        }
+        Commands::SetName { name, description } => {
+            todolist.set_name(name, description);
+            todolist.list_tasks();
+        }
+        Commands::SetStatus { id, status } => {
+            todolist.set_status(id, status);
+            todolist.list_tasks();
+        }
+        Commands::Tag { id, add, remove } => {
+            if let Some(tags) = todolist.tag_task(id, &add, &remove) {
+                println!("Tags: {}", tags.join(", "));
+            }
+        }
+        Commands::Link { id, add, remove } => {
+            if let Some(links) = todolist.link_task(id, &add, &remove) {
+                println!("Links: {}", links.join(", "));
+            }
+        }
+        Commands::Show { id } => {
+            todolist.show_task(id);
+        }
+        Commands::Remove { id, silent } => {
+            if !todolist.remove_task(id) {
+                process::exit(1);
+            }
+            if !silent {
+                todolist.list_tasks();
+            }
+        }
+        Commands::Transfer { id, to } => {
+            let Some(new_id) = todolist.transfer_task(id, &to) else {
+                process::exit(1);
+            };
+            println!("Moved to {} as task {}", to.display(), new_id);
+            todolist.list_tasks();
+        }
+        Commands::Complete {
+            ids,
+            remove,
+            yes,
+            journal,
+            cascade_parent,
+            instead_of,
+            silent,
+        } => {
+            todolist.journal |= journal;
+            todolist.cascade_parent = cascade_parent;
+            if instead_of.is_some() && ids.len() != 1 {
+                eprintln!("--instead-of takes a single task to complete");
+                process::exit(1);
+            }
+            let ids = todolist.confirm_completion(&ids, yes, &mut io::stdin().lock());
+            if let Some(wrong) = instead_of {
+                let [id] = ids[..] else {
+                    println!("Aborted");
+                    process::exit(1);
+                };
+                if !todolist.complete_instead_of(id, wrong) {
+                    process::exit(1);
+                }
+            } else if remove {
+                if !todolist.complete_and_remove_tasks(&ids) {
+                    process::exit(1);
+                }
+            } else if !todolist.complete_tasks(&ids) {
+                process::exit(1);
+            }
+            if !silent {
+                todolist.list_tasks();
+            }
+        }
+        Commands::Progress { id, percent } => {
+            todolist.set_progress(id, percent);
             todolist.list_tasks();
         }
-        Commands::List { completed, pending } => {
-            if completed {
-                todolist.list_completed_tasks();
+        Commands::AddProgress { id, percent } => {
+            todolist.add_progress(id, percent);
+            todolist.list_tasks();
+        }
+        Commands::Stats { json, by_day } => {
+            let stats = todolist.stats();
+            if let Some(days) = by_day {
+                todolist.display_completions_by_day(days);
+            } else if json {
+                match stats.to_json() {
+                    Ok(json) => println!("{}", json),
+                    Err(e) => eprintln!("Serialization failed {}", e),
+                }
+            } else {
+                stats.display();
+            }
+        }
+        Commands::Start { id } => {
+            todolist.start_task(id);
+            todolist.list_tasks();
+        }
+        Commands::Stop { id } => {
+            todolist.stop_task(id);
+            todolist.list_tasks();
+        }
+        Commands::List {
+            completed,
+            pending,
+            fields,
+            sort,
+            none_first,
+            limit,
+            flat,
+            after_id,
+            before_id,
+            show_deferred,
+            color_by,
+            source,
+            modified_today,
+            has_due,
+            no_due,
+            due_within,
+            done_between,
+            status,
+            tags,
+            excluded_tags,
+            json,
+            json_pretty,
+            json_array,
+            porcelain,
+            null,
+            separator,
+            output,
+        } => {
+            todolist.output = if json {
+                OutputEnum::Json
+            } else if json_pretty {
+                OutputEnum::JsonPretty
+            } else if json_array {
+                OutputEnum::JsonArray
+            } else if porcelain {
+                OutputEnum::Porcelain
+            } else if null {
+                OutputEnum::Null
+            } else {
+                OutputEnum::Text
+            };
+            todolist.separator = separator;
+            if output.is_some() {
+                // A file is not a terminal, so no colors unless forced and no wrapping
+                todolist.color = cli.color.enabled(false);
+                todolist.width = None;
+            }
+            todolist.output_path = output;
+            let mut filter = TaskFilter::new();
+            if let Some(duration) = due_within {
+                let now = Local::now();
+                filter = filter.pending().due_between(now, now + duration);
+            }
+            if let Some(source) = &source {
+                filter = filter.source(source);
+            }
+            if let Some(id) = after_id {
+                filter = filter.after_id(id);
+            }
+            if let Some(id) = before_id {
+                filter = filter.before_id(id);
+            }
+            if !show_deferred {
+                filter = filter.visible_at(Local::now());
+            }
+            if modified_today {
+                filter = filter.modified_on(Local::now().date_naive());
+            }
+            if has_due {
+                filter = filter.has_due();
+            } else if no_due {
+                filter = filter.no_due();
+            }
+            if let Some(status) = status {
+                filter = filter.status(status);
+            }
+            for tag in &tags {
+                filter = filter.tag(tag);
+            }
+            for tag in &excluded_tags {
+                filter = filter.exclude_tag(tag);
+            }
+            if let Some([start, end]) = done_between.as_deref() {
+                if start > end {
+                    eprintln!("The start date {} is after the end date {}", start, end);
+                    process::exit(1);
+                }
+                filter = filter.completed_between(*start, *end);
+            }
+            todolist.filter = filter;
+            todolist.fields = fields;
+            todolist.sort = sort;
+            todolist.none_first = none_first;
+            todolist.limit = limit;
+            todolist.flat = flat;
+            todolist.color_by = color_by;
+            let view = if completed || done_between.is_some() {
+                ListFilterEnum::Completed
             } else if pending {
-                todolist.list_pending_tasks();
+                ListFilterEnum::Pending
             } else {
-                todolist.list_tasks();
+                config.default_list_filter
+            };
+            match view {
+                ListFilterEnum::All => todolist.list_tasks(),
+                ListFilterEnum::Pending => todolist.list_pending_tasks(),
+                ListFilterEnum::Completed => todolist.list_completed_tasks(),
             }
         }
+        Commands::CompleteAll { yes, journal } => {
+            todolist.journal |= journal;
+            todolist.complete_all_tasks(yes, &mut io::stdin().lock());
+            todolist.list_tasks();
+        }
+        Commands::Purge { yes } => {
+            todolist.purge_tasks(yes, &mut io::stdin().lock());
+        }
+        Commands::Review => {
+            todolist.review_tasks(&mut io::stdin().lock(), Local::now());
+        }
+        Commands::Random { weighted } => {
+            let mut rng = cli
+                .seed
+                .map_or_else(fastrand::Rng::new, fastrand::Rng::with_seed);
+            match todolist.pick_task(&mut rng, weighted) {
+                Some(task) => todolist.display_task(task),
+                None => println!("No pending task"),
+            }
+        }
+        Commands::RenumberIds => {
+            todolist.renumber_ids();
+            todolist.list_tasks();
+        }
+        Commands::History => match todolist.history() {
+            Ok(lines) => {
+                for line in lines {
+                    println!("{}", line);
+                }
+            }
+            Err(e) => eprintln!("IO error {}", e),
+        },
         Commands::Reset => {
             todolist.reset_tasks();
             todolist.list_tasks();
         }
-        Commands::Export { format } => {
-            todolist.export_tasks(format);
+        Commands::Duplicates { remove } => {
+            todolist.display_duplicates();
+            if remove {
+                let count = todolist.remove_duplicates();
+                println!("Removed {} tasks", count);
+            }
+        }
+        Commands::Clear { before } => {
+            let count = todolist.clear_completed_tasks(before);
+            println!("Removed {} tasks", count);
+        }
+        Commands::Archive => {
+            todolist.archive_completed_tasks();
+        }
+        Commands::Backup => {
+            todolist.backup_tasks();
+        }
+        Commands::RestoreBackup { which } => {
+            todolist.restore_backup(which, &mut io::stdin().lock());
+            todolist.list_tasks();
+        }
+        Commands::Status { prompt } => {
+            if let Err(e) = todolist.write_status(&mut io::stdout(), prompt) {
+                eprintln!("IO error {}", e);
+            }
+        }
+        Commands::Burndown { days } => {
+            todolist.display_burndown(days);
+        }
+        Commands::Forecast { id, count } => {
+            todolist.forecast_task(id, count);
+        }
+        Commands::Export {
+            format,
+            out,
+            append,
+            sort,
+            force,
+            indent,
+            timestamps,
+        } => {
+            if let Some(timestamps) = timestamps {
+                todolist.timestamps = timestamps;
+            }
+            todolist.export_tasks(
+                format,
+                &ExportOptions {
+                    out,
+                    append,
+                    sort,
+                    force,
+                    indent,
+                },
+            );
+        }
+        Commands::Validate { .. } => unreachable!("validated before loading"),
+        Commands::Diff { other } => {
+            todolist.diff_tasks(&other);
+        }
+        Commands::Import { file, input_format } => {
+            todolist.import_tasks(&file, input_format);
         }
     }
 }
@@ -0,0 +1,28 @@
+use std::io::{self, BufRead, Write};
+
+/// Ask a yes/no question, anything but `y` or `yes` meaning no
+pub fn confirm(question: &str, input: &mut impl BufRead) -> bool {
+    print!("{} [y/N] ", question);
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    if input.read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Ask for a word to be typed back, true only when the answer is one of `expected` exactly
+pub fn confirm_typed(question: &str, expected: &[&str], input: &mut impl BufRead) -> bool {
+    ask(question, input).is_some_and(|answer| expected.contains(&answer.as_str()))
+}
+
+/// Ask for a line of input, trimmed, `None` once the input is exhausted
+pub fn ask(question: &str, input: &mut impl BufRead) -> Option<String> {
+    print!("{} ", question);
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    match input.read_line(&mut answer) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(answer.trim().to_string()),
+    }
+}
@@ -0,0 +1,288 @@
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use std::{io, path::Path};
+
+use crate::exporter::{CsvTask, ExportError};
+use crate::store::Store;
+use crate::task::Task;
+use crate::todolist::{LoadError, TodoList};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS tasks (
+        id INTEGER NOT NULL,
+        title TEXT NOT NULL,
+        done INTEGER NOT NULL,
+        created_at TEXT NOT NULL,
+        completed_at TEXT,
+        priority TEXT,
+        due_at TEXT,
+        recurrence TEXT,
+        description TEXT,
+        started_at TEXT,
+        total_seconds INTEGER NOT NULL,
+        progress INTEGER NOT NULL,
+        title_history TEXT NOT NULL,
+        source TEXT,
+        remind_at TEXT,
+        parent_id INTEGER,
+        tags TEXT NOT NULL,
+        status TEXT NOT NULL,
+        escalated_at TEXT,
+        modified_at TEXT,
+        links TEXT NOT NULL,
+        lead_time_seconds INTEGER,
+        hide_until TEXT
+    );
+    CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value TEXT);
+    CREATE TABLE IF NOT EXISTS log (line TEXT NOT NULL);
+";
+
+const COLUMNS: &str = "id, title, done, created_at, completed_at, priority, due_at, recurrence, \
+    description, started_at, total_seconds, progress, title_history, source, remind_at, \
+    parent_id, tags, status, escalated_at, modified_at, links, \
+    lead_time_seconds, hide_until";
+
+/// A SQLite database with one row per task, lists being joined with `;` as in CSV
+#[derive(Debug)]
+pub struct SqliteStore {
+    connection: Connection,
+}
+
+/// The serde form of a value as text: strings as is, `None` as NULL
+fn to_text<T: Serialize>(value: &T) -> Option<String> {
+    match serde_json::to_value(value).ok()? {
+        Value::Null => None,
+        Value::String(text) => Some(text),
+        other => Some(other.to_string()),
+    }
+}
+
+fn from_text<T: DeserializeOwned>(row: &Row, column: &str) -> rusqlite::Result<T> {
+    let text: Option<String> = row.get(column)?;
+    serde_json::from_value(text.map_or(Value::Null, Value::String)).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, e.into())
+    })
+}
+
+fn load_error(e: rusqlite::Error) -> LoadError {
+    LoadError::DeserializationError(e.to_string())
+}
+
+fn save_error(e: rusqlite::Error) -> ExportError {
+    ExportError::SerializationError(e.to_string())
+}
+
+impl SqliteStore {
+    /// Open the database at `path`, creating it and its tables when needed
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(SqliteStore { connection })
+    }
+
+    fn read_task(row: &Row) -> rusqlite::Result<Task> {
+        let task = CsvTask {
+            id: row.get("id")?,
+            title: row.get("title")?,
+            done: row.get("done")?,
+            created_at: from_text(row, "created_at")?,
+            completed_at: from_text(row, "completed_at")?,
+            priority: from_text(row, "priority")?,
+            due_at: from_text(row, "due_at")?,
+            recurrence: from_text(row, "recurrence")?,
+            description: row.get("description")?,
+            started_at: from_text(row, "started_at")?,
+            total_seconds: row.get("total_seconds")?,
+            progress: row.get("progress")?,
+            title_history: row.get("title_history")?,
+            source: row.get("source")?,
+            remind_at: from_text(row, "remind_at")?,
+            parent_id: row.get("parent_id")?,
+            tags: row.get("tags")?,
+            status: from_text(row, "status")?,
+            escalated_at: from_text(row, "escalated_at")?,
+            modified_at: from_text(row, "modified_at")?,
+            links: row.get("links")?,
+            lead_time_seconds: row.get("lead_time_seconds")?,
+            hide_until: from_text(row, "hide_until")?,
+        };
+        Ok(task.into())
+    }
+
+    fn meta(&self, key: &str) -> rusqlite::Result<Option<String>> {
+        self.connection
+            .query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| {
+                row.get(0)
+            })
+            .optional()
+            .map(Option::flatten)
+    }
+}
+
+impl Store for SqliteStore {
+    fn load(&self) -> Result<Option<TodoList>, LoadError> {
+        let mut statement = self
+            .connection
+            .prepare(&format!("SELECT {} FROM tasks ORDER BY rowid", COLUMNS))
+            .map_err(load_error)?;
+        let tasks = statement
+            .query_map([], Self::read_task)
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<Task>>>())
+            .map_err(load_error)?;
+        let name = self.meta("name").map_err(load_error)?;
+        let description = self.meta("description").map_err(load_error)?;
+        if tasks.is_empty() && name.is_none() && description.is_none() {
+            return Ok(None);
+        }
+        let mut todolist = TodoList::empty(Path::new(""));
+        todolist.tasks = tasks;
+        todolist.name = name;
+        todolist.description = description;
+        Ok(Some(todolist))
+    }
+
+    fn save(&self, todolist: &TodoList) -> Result<(), ExportError> {
+        let transaction = self
+            .connection
+            .unchecked_transaction()
+            .map_err(save_error)?;
+        transaction
+            .execute("DELETE FROM tasks", [])
+            .map_err(save_error)?;
+        {
+            let mut insert = transaction
+                .prepare(&format!(
+                    "INSERT INTO tasks ({}) VALUES \
+                     (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23)",
+                    COLUMNS
+                ))
+                .map_err(save_error)?;
+            for task in todolist.tasks.iter() {
+                let row = CsvTask::from(task);
+                insert
+                    .execute(params![
+                        row.id,
+                        row.title,
+                        row.done,
+                        to_text(&row.created_at),
+                        to_text(&row.completed_at),
+                        to_text(&row.priority),
+                        to_text(&row.due_at),
+                        to_text(&row.recurrence),
+                        row.description,
+                        to_text(&row.started_at),
+                        row.total_seconds,
+                        row.progress,
+                        row.title_history,
+                        row.source,
+                        to_text(&row.remind_at),
+                        row.parent_id,
+                        row.tags,
+                        to_text(&row.status),
+                        to_text(&row.escalated_at),
+                        to_text(&row.modified_at),
+                        row.links,
+                        row.lead_time_seconds,
+                        to_text(&row.hide_until),
+                    ])
+                    .map_err(save_error)?;
+            }
+        }
+        for (key, value) in [
+            ("name", &todolist.name),
+            ("description", &todolist.description),
+        ] {
+            transaction
+                .execute(
+                    "INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)",
+                    params![key, value],
+                )
+                .map_err(save_error)?;
+        }
+        transaction.commit().map_err(save_error)
+    }
+
+    fn append_log(&self, _todolist: &TodoList, line: &str) -> io::Result<()> {
+        self.connection
+            .execute("INSERT INTO log (line) VALUES (?1)", [line])
+            .map(|_| ())
+            .map_err(io::Error::other)
+    }
+
+    fn read_log(&self) -> io::Result<Vec<String>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT line FROM log ORDER BY rowid")
+            .map_err(io::Error::other)?;
+        statement
+            .query_map([], |row| row.get(0))
+            .and_then(|rows| rows.collect())
+            .map_err(io::Error::other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::{PriorityEnum, StatusEnum};
+    use chrono::{Local, TimeZone};
+
+    fn open(path: &Path) -> TodoList {
+        TodoList::open(path, Box::new(SqliteStore::open(path).unwrap())).unwrap()
+    }
+
+    #[test]
+    fn test_transfer_into_a_sqlite_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let db = dir.path().join("tasks.db");
+        open(&db).add_task("already there".to_string(), None);
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("moves".to_string(), None);
+
+        assert_eq!(todolist.transfer_task(1, &db), Some(2));
+        let titles: Vec<String> = open(&db).tasks.into_iter().map(|task| task.title).collect();
+        assert_eq!(titles, vec!["already there", "moves"]);
+    }
+
+    #[test]
+    fn test_sqlite_add_complete_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tasks.db");
+        let mut todolist = open(&path);
+        assert!(todolist.tasks.is_empty());
+        todolist.add_task("task 1".to_string(), Some(PriorityEnum::High));
+        todolist.add_task("task 2".to_string(), None);
+        todolist.tag_task(2, &["home".to_string(), "errands".to_string()], &[]);
+        let now = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        todolist.complete_task_at(1, now);
+        todolist.set_name("Chores".to_string(), None);
+
+        let todolist = open(&path);
+        assert_eq!(todolist.name, Some("Chores".to_string()));
+        assert_eq!(todolist.tasks.len(), 2);
+        assert_eq!(todolist.tasks[0].priority, Some(PriorityEnum::High));
+        assert!(todolist.tasks[0].done);
+        assert_eq!(todolist.tasks[0].status, StatusEnum::Done);
+        assert_eq!(todolist.tasks[0].completed_at, Some(now));
+        assert_eq!(todolist.tasks[1].tags, vec!["home", "errands"]);
+        assert_eq!(todolist.tasks[1].status, StatusEnum::Todo);
+        assert_eq!(todolist.history().unwrap().len(), 3);
+        assert!(!path.with_extension("json").exists());
+    }
+
+    #[test]
+    fn test_sqlite_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tasks.db");
+        let mut todolist = open(&path);
+        todolist.add_task("task 1".to_string(), None);
+        todolist.add_task("task 2".to_string(), None);
+        todolist.remove_task(1);
+
+        let todolist = open(&path);
+        let ids: Vec<_> = todolist.tasks.iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![2]);
+    }
+}
@@ -0,0 +1,141 @@
+use serde::Serialize;
+
+use crate::task::{PriorityEnum, Task};
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Stats {
+    pub total: usize,
+    pub done: usize,
+    /// Share of done tasks, in percent
+    pub completion: f64,
+    /// Share of work done counting each task's progress, in percent
+    pub weighted_completion: f64,
+    /// Mean wall-clock time from creation to completion of the done tasks, in seconds
+    pub average_lead_time_seconds: Option<f64>,
+    /// Done and pending counts for high, medium, low and no priority, in that order
+    pub by_priority: [PriorityCounts; 4],
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct PriorityCounts {
+    pub priority: Option<PriorityEnum>,
+    pub done: usize,
+    pub pending: usize,
+}
+
+impl PriorityCounts {
+    fn new(priority: Option<PriorityEnum>) -> Self {
+        PriorityCounts {
+            priority,
+            done: 0,
+            pending: 0,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self.priority {
+            Some(PriorityEnum::High) => "high",
+            Some(PriorityEnum::Medium) => "medium",
+            Some(PriorityEnum::Low) => "low",
+            None => "none",
+        }
+    }
+}
+
+impl Stats {
+    pub fn from_tasks(tasks: &[Task]) -> Self {
+        let total = tasks.len();
+        let mut done = 0;
+        let mut progress: u32 = 0;
+        let mut lead_times: Vec<i64> = vec![];
+        let mut by_priority = [
+            PriorityCounts::new(Some(PriorityEnum::High)),
+            PriorityCounts::new(Some(PriorityEnum::Medium)),
+            PriorityCounts::new(Some(PriorityEnum::Low)),
+            PriorityCounts::new(None),
+        ];
+        for task in tasks {
+            let bucket = match task.priority {
+                Some(PriorityEnum::High) => &mut by_priority[0],
+                Some(PriorityEnum::Medium) => &mut by_priority[1],
+                Some(PriorityEnum::Low) => &mut by_priority[2],
+                None => &mut by_priority[3],
+            };
+            if task.done {
+                done += 1;
+                progress += 100;
+                bucket.done += 1;
+                lead_times.extend(task.lead_time_seconds);
+            } else {
+                progress += u32::from(task.progress);
+                bucket.pending += 1;
+            }
+        }
+        let (completion, weighted_completion) = if total == 0 {
+            (0.0, 0.0)
+        } else {
+            (
+                done as f64 * 100.0 / total as f64,
+                progress as f64 / total as f64,
+            )
+        };
+        let average_lead_time_seconds = if lead_times.is_empty() {
+            None
+        } else {
+            Some(lead_times.iter().sum::<i64>() as f64 / lead_times.len() as f64)
+        };
+        Stats {
+            total,
+            done,
+            completion,
+            weighted_completion,
+            average_lead_time_seconds,
+            by_priority,
+        }
+    }
+
+    /// The per priority counts as a table, columns aligned to the widest count
+    pub fn render_by_priority(&self) -> Vec<String> {
+        let width = self
+            .by_priority
+            .iter()
+            .flat_map(|counts| [counts.done, counts.pending])
+            .map(|count| count.to_string().len())
+            .max()
+            .unwrap_or(0)
+            .max("pending".len());
+        let mut lines = vec![format!(
+            "{:<8} {:>width$} {:>width$}",
+            "Priority", "done", "pending"
+        )];
+        for counts in self.by_priority.iter() {
+            lines.push(format!(
+                "{:<8} {:>width$} {:>width$}",
+                counts.label(),
+                counts.done,
+                counts.pending
+            ));
+        }
+        lines
+    }
+
+    /// The figures as one JSON object, for dashboards
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    pub fn display(&self) {
+        println!(
+            "Tasks done: {}/{} ({:.0}%)",
+            self.done, self.total, self.completion
+        );
+        println!("Weighted completion: {:.0}%", self.weighted_completion);
+        if let Some(seconds) = self.average_lead_time_seconds {
+            println!("Average lead time: {:.1} days", seconds / 86400.0);
+        }
+        println!();
+        for line in self.render_by_priority() {
+            println!("{}", line);
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use clap::ValueEnum;
+use std::{
+    cell::RefCell,
+    fmt, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::exporter::{ExportError, Exporter, JsonExporter};
+use crate::todolist::{LoadError, TodoList};
+
+#[derive(Clone, Copy, ValueEnum, PartialEq, Debug)]
+pub enum BackendEnum {
+    /// A JSON file
+    Json,
+    /// A SQLite database, when built with the `sqlite` feature
+    Sqlite,
+}
+
+impl BackendEnum {
+    /// SQLite for `.db` and `.sqlite` paths, JSON otherwise
+    pub fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("db" | "sqlite") => BackendEnum::Sqlite,
+            _ => BackendEnum::Json,
+        }
+    }
+
+    /// The store of the list saved at `path` with this backend
+    pub fn open_store(self, path: &Path) -> Result<Box<dyn Store>, LoadError> {
+        match self {
+            BackendEnum::Json => Ok(Box::new(FileStore::new(path.to_path_buf()))),
+            #[cfg(feature = "sqlite")]
+            BackendEnum::Sqlite => crate::sqlite::SqliteStore::open(path)
+                .map(|store| Box::new(store) as Box<dyn Store>)
+                .map_err(|e| LoadError::IoError(io::Error::other(e))),
+            #[cfg(not(feature = "sqlite"))]
+            BackendEnum::Sqlite => Err(LoadError::IoError(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "SQLite storage needs a build with the sqlite feature",
+            ))),
+        }
+    }
+}
+
+/// Where a list and its event log are persisted
+pub trait Store: fmt::Debug {
+    /// The saved list, `None` when nothing was saved yet
+    fn load(&self) -> Result<Option<TodoList>, LoadError>;
+    fn save(&self, todolist: &TodoList) -> Result<(), ExportError>;
+    /// Append to the event log, `todolist` giving the file mode to use
+    fn append_log(&self, todolist: &TodoList, line: &str) -> io::Result<()>;
+    /// The event log lines, oldest first
+    fn read_log(&self) -> io::Result<Vec<String>>;
+}
+
+/// The JSON save file, with the event log next to it in `<path>.log`
+#[derive(Debug)]
+pub struct FileStore {
+    pub path: PathBuf,
+    /// Refuse files with fields unknown to the schema
+    pub strict: bool,
+}
+
+impl FileStore {
+    pub fn new(path: PathBuf) -> Self {
+        FileStore {
+            path,
+            strict: false,
+        }
+    }
+
+    pub fn log_path(&self) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".log");
+        PathBuf::from(path)
+    }
+}
+
+#[cfg(unix)]
+fn apply_file_mode(path: &Path, mode: Option<u32>) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    match mode {
+        Some(mode) => fs::set_permissions(path, fs::Permissions::from_mode(mode)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+fn apply_file_mode(_path: &Path, _mode: Option<u32>) -> io::Result<()> {
+    Ok(())
+}
+
+/// Open `path` for appending, creating it if needed, with the configured file mode applied
+pub fn open_append(path: &Path, mode: Option<u32>) -> io::Result<fs::File> {
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    apply_file_mode(path, mode)?;
+    Ok(file)
+}
+
+impl Store for FileStore {
+    fn load(&self) -> Result<Option<TodoList>, LoadError> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(LoadError::IoError(e)),
+        };
+        if !self.strict {
+            return serde_json::from_str(&content)
+                .map(Some)
+                .map_err(|e| LoadError::DeserializationError(e.to_string()));
+        }
+        let mut unknown_fields = vec![];
+        let mut deserializer = serde_json::Deserializer::from_str(&content);
+        let todolist = serde_ignored::deserialize(&mut deserializer, |field| {
+            unknown_fields.push(field.to_string())
+        })
+        .map_err(|e| LoadError::DeserializationError(e.to_string()))?;
+        if !unknown_fields.is_empty() {
+            return Err(LoadError::UnknownFields(unknown_fields));
+        }
+        Ok(Some(todolist))
+    }
+
+    fn save(&self, todolist: &TodoList) -> Result<(), ExportError> {
+        JsonExporter::default().export_to_path(todolist, &self.path)?;
+        apply_file_mode(&self.path, todolist.file_mode).map_err(ExportError::IoError)
+    }
+
+    fn append_log(&self, todolist: &TodoList, line: &str) -> io::Result<()> {
+        open_append(&self.log_path(), todolist.file_mode)
+            .and_then(|mut file| writeln!(file, "{}", line))
+    }
+
+    fn read_log(&self) -> io::Result<Vec<String>> {
+        match fs::read_to_string(self.log_path()) {
+            Ok(content) => Ok(content.lines().map(str::to_string).collect()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(vec![]),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Keeps the list in memory as JSON, for tests and embedding
+#[derive(Default, Debug)]
+pub struct MemoryStore {
+    saved: RefCell<Option<String>>,
+    log: RefCell<Vec<String>>,
+}
+
+impl Store for MemoryStore {
+    fn load(&self) -> Result<Option<TodoList>, LoadError> {
+        self.saved
+            .borrow()
+            .as_deref()
+            .map(serde_json::from_str)
+            .transpose()
+            .map_err(|e| LoadError::DeserializationError(e.to_string()))
+    }
+
+    fn save(&self, todolist: &TodoList) -> Result<(), ExportError> {
+        let json = serde_json::to_string(todolist)
+            .map_err(|e| ExportError::SerializationError(e.to_string()))?;
+        self.saved.replace(Some(json));
+        Ok(())
+    }
+
+    fn append_log(&self, _todolist: &TodoList, line: &str) -> io::Result<()> {
+        self.log.borrow_mut().push(line.to_string());
+        Ok(())
+    }
+
+    fn read_log(&self) -> io::Result<Vec<String>> {
+        Ok(self.log.borrow().clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_store_round_trip() {
+        let path = Path::new("memory-store-test.json");
+        let mut todolist = TodoList::open(path, Box::<MemoryStore>::default()).unwrap();
+        assert!(todolist.tasks.is_empty());
+        todolist.add_task("task 1".to_string(), None);
+        todolist.add_task("task 2".to_string(), None);
+        todolist.complete_task(2);
+
+        let TodoList { store, .. } = todolist;
+        let todolist = TodoList::open(path, store).unwrap();
+        assert_eq!(todolist.tasks.len(), 2);
+        assert!(todolist.tasks[1].done);
+        assert_eq!(todolist.history().unwrap().len(), 3);
+        assert!(!path.exists());
+        assert!(!FileStore::new(path.to_path_buf()).log_path().exists());
+    }
+
+    #[test]
+    fn test_file_store_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileStore::new(dir.path().join("todo.json"));
+        assert!(store.load().unwrap().is_none());
+        assert!(store.read_log().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_backend_for_path() {
+        assert_eq!(
+            BackendEnum::for_path(Path::new("tasks.db")),
+            BackendEnum::Sqlite
+        );
+        assert_eq!(
+            BackendEnum::for_path(Path::new("tasks.sqlite")),
+            BackendEnum::Sqlite
+        );
+        assert_eq!(
+            BackendEnum::for_path(Path::new("todo.json")),
+            BackendEnum::Json
+        );
+        assert_eq!(BackendEnum::for_path(Path::new("todo")), BackendEnum::Json);
+    }
+}
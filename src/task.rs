@@ -1,46 +1,685 @@
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Days, Local, Months};
 use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
+use std::{env, fs};
 
-#[derive(Serialize, Deserialize, Clone, ValueEnum, PartialEq, Debug)]
+use crate::color::{
+    BLUE, ColorByEnum, DUE_SOON_DAYS, GREEN, MAGENTA, RED, Theme, YELLOW, paint, tag_color,
+};
+
+#[derive(Serialize, Deserialize, Clone, ValueEnum, PartialEq, Eq, PartialOrd, Ord, Debug)]
 pub enum PriorityEnum {
     High,
     Medium,
     Low,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Where a task stands, `done` mirroring `Done` for older files and tools
+#[derive(Serialize, Deserialize, Clone, Copy, Default, ValueEnum, PartialEq, Debug)]
+pub enum StatusEnum {
+    #[default]
+    Todo,
+    Doing,
+    /// Waiting on someone or something else
+    Waiting,
+    Done,
+}
+
+#[derive(Serialize, Deserialize, Clone, ValueEnum, PartialEq, Debug)]
+pub enum RecurrenceEnum {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl RecurrenceEnum {
+    pub fn next(&self, date: DateTime<Local>) -> Option<DateTime<Local>> {
+        match self {
+            RecurrenceEnum::Daily => date.checked_add_days(Days::new(1)),
+            RecurrenceEnum::Weekly => date.checked_add_days(Days::new(7)),
+            RecurrenceEnum::Monthly => date.checked_add_months(Months::new(1)),
+        }
+    }
+}
+
+/// How precisely the list and the CSV and Markdown exports show dates
+#[derive(Deserialize, Clone, Copy, Default, ValueEnum, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum TimestampEnum {
+    #[default]
+    Datetime,
+    /// The day only
+    Date,
+    /// No dates but the due day
+    None,
+}
+
+impl TimestampEnum {
+    /// The date as shown, `None` when left out
+    pub fn format(&self, date: DateTime<Local>) -> Option<String> {
+        match self {
+            TimestampEnum::Datetime => Some(date.to_string()),
+            TimestampEnum::Date => Some(date.format("%Y-%m-%d").to_string()),
+            TimestampEnum::None => None,
+        }
+    }
+
+    /// A due date as shown, its day at least as it is part of the task
+    pub fn format_due(&self, date: DateTime<Local>) -> String {
+        match self {
+            TimestampEnum::Datetime => date.to_string(),
+            TimestampEnum::Date | TimestampEnum::None => date.format("%Y-%m-%d").to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum, PartialEq, Debug)]
+pub enum SortEnum {
+    Priority,
+    Due,
+    Created,
+}
+
+#[derive(Clone, Copy, ValueEnum, PartialEq, Debug)]
+pub enum FieldEnum {
+    Id,
+    Title,
+    Status,
+    Priority,
+    Due,
+    Created,
+    Completed,
+    Description,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct Task {
     pub id: i32,
     pub title: String,
     pub done: bool,
     pub created_at: DateTime<Local>,
     pub completed_at: Option<DateTime<Local>>,
+    // Fields below were added after the first file format, older files may lack them
+    #[serde(default)]
     pub priority: Option<PriorityEnum>,
+    #[serde(default)]
+    pub due_at: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub recurrence: Option<RecurrenceEnum>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub started_at: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub total_seconds: i64,
+    #[serde(default)]
+    pub progress: u8,
+    #[serde(default)]
+    pub title_history: Vec<String>,
+    /// The device or project the task was created from
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub remind_at: Option<DateTime<Local>>,
+    /// The task this one is a subtask of
+    #[serde(default)]
+    pub parent_id: Option<i32>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub status: StatusEnum,
+    /// When the priority was last raised for the task's age
+    #[serde(default)]
+    pub escalated_at: Option<DateTime<Local>>,
+    /// When the task was last changed, the creation date for files predating it
+    #[serde(default)]
+    pub modified_at: Option<DateTime<Local>>,
+    /// URLs of the tickets or documents the task refers to
+    #[serde(default)]
+    pub links: Vec<String>,
+    /// Wall-clock time from creation to completion, unlike the tracked `total_seconds`
+    #[serde(default)]
+    pub lead_time_seconds: Option<i64>,
+    /// Left out of `list` until then, unlike the due date which only orders and flags
+    #[serde(default)]
+    pub hide_until: Option<DateTime<Local>>,
+}
+
+/// A task under construction, the id being given when it is added to a list
+///
+/// ```
+/// use todo_cli::{store::MemoryStore, task::{PriorityEnum, TaskBuilder}, todolist::TodoList};
+///
+/// let mut todolist = TodoList::open("todo.json".as_ref(), Box::<MemoryStore>::default()).unwrap();
+/// let id = todolist.add(TaskBuilder::new().title("Buy milk").priority(PriorityEnum::Low).tag("home"));
+/// assert_eq!(todolist.tasks[0].id, id);
+/// ```
+#[derive(Default, Clone, Debug)]
+pub struct TaskBuilder {
+    title: String,
+    priority: Option<PriorityEnum>,
+    due_at: Option<DateTime<Local>>,
+    recurrence: Option<RecurrenceEnum>,
+    description: Option<String>,
+    source: Option<String>,
+    remind_at: Option<DateTime<Local>>,
+    parent_id: Option<i32>,
+    tags: Vec<String>,
+    links: Vec<String>,
+}
+
+impl TaskBuilder {
+    pub fn new() -> Self {
+        TaskBuilder::default()
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// Takes a priority or an `Option`, `None` leaving the task without one
+    pub fn priority(mut self, priority: impl Into<Option<PriorityEnum>>) -> Self {
+        self.priority = priority.into();
+        self
+    }
+
+    pub fn due(mut self, due_at: impl Into<Option<DateTime<Local>>>) -> Self {
+        self.due_at = due_at.into();
+        self
+    }
+
+    pub fn recurrence(mut self, recurrence: impl Into<Option<RecurrenceEnum>>) -> Self {
+        self.recurrence = recurrence.into();
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<Option<String>>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    pub fn source(mut self, source: impl Into<Option<String>>) -> Self {
+        self.source = source.into();
+        self
+    }
+
+    pub fn remind(mut self, remind_at: impl Into<Option<DateTime<Local>>>) -> Self {
+        self.remind_at = remind_at.into();
+        self
+    }
+
+    pub fn parent(mut self, parent_id: impl Into<Option<i32>>) -> Self {
+        self.parent_id = parent_id.into();
+        self
+    }
+
+    /// Add a tag, chaining adds each tag once
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        let tag = tag.into();
+        if !self.tags.contains(&tag) {
+            self.tags.push(tag);
+        }
+        self
+    }
+
+    /// Add a link, chaining adds each link once
+    pub fn link(mut self, link: impl Into<String>) -> Self {
+        let link = link.into();
+        if !self.links.contains(&link) {
+            self.links.push(link);
+        }
+        self
+    }
+
+    /// A pending task with this id, created now
+    pub fn build(self, id: i32) -> Task {
+        let mut task = Task::new(id, self.title, self.priority);
+        task.due_at = self.due_at;
+        task.recurrence = self.recurrence;
+        task.description = self.description;
+        task.source = self.source;
+        task.remind_at = self.remind_at;
+        task.parent_id = self.parent_id;
+        task.tags = self.tags;
+        task.links = self.links;
+        task
+    }
+}
+
+/// The machine hostname, used as the default source of new tasks
+pub fn hostname() -> Option<String> {
+    env::var("HOSTNAME")
+        .or_else(|_| env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
 }
 
 impl Task {
-    pub fn display(&self) {
-        let priority = match self.priority {
-            Some(PriorityEnum::High) => " - Priority high",
-            Some(PriorityEnum::Medium) => " - Priority medium",
-            Some(PriorityEnum::Low) => " - Priority low",
-            None => "",
+    pub fn new(id: i32, title: String, priority: Option<PriorityEnum>) -> Self {
+        let now = Local::now();
+        Task {
+            id,
+            title,
+            done: false,
+            created_at: now,
+            completed_at: None,
+            priority,
+            due_at: None,
+            recurrence: None,
+            description: None,
+            started_at: None,
+            total_seconds: 0,
+            progress: 0,
+            title_history: vec![],
+            source: None,
+            remind_at: None,
+            parent_id: None,
+            tags: vec![],
+            status: StatusEnum::Todo,
+            escalated_at: None,
+            modified_at: Some(now),
+            links: vec![],
+            lead_time_seconds: None,
+            hide_until: None,
+        }
+    }
+
+    pub fn mark_modified(&mut self, now: DateTime<Local>) {
+        self.modified_at = Some(now);
+    }
+
+    /// Raise the priority one level if pending and untouched by escalation for more than `days`
+    /// days, counting from creation, returning whether it was raised
+    pub fn escalate(&mut self, days: u32, now: DateTime<Local>) -> bool {
+        let Some(cutoff) = now.checked_sub_days(Days::new(days.into())) else {
+            return false;
+        };
+        if self.done || self.escalated_at.unwrap_or(self.created_at) >= cutoff {
+            return false;
+        }
+        let raised = match self.priority {
+            Some(PriorityEnum::Low) => PriorityEnum::Medium,
+            Some(PriorityEnum::Medium) => PriorityEnum::High,
+            _ => return false,
         };
+        self.priority = Some(raised);
+        self.escalated_at = Some(now);
+        self.mark_modified(now);
+        true
+    }
+
+    pub fn complete(&mut self, now: DateTime<Local>) {
+        self.stop_timer(now);
+        self.done = true;
+        self.status = StatusEnum::Done;
+        self.completed_at = Some(now);
+        self.lead_time_seconds = Some((now - self.created_at).num_seconds().max(0));
+        self.mark_modified(now);
+    }
+
+    /// Whether the task is still hidden at `now`
+    pub fn is_deferred(&self, now: DateTime<Local>) -> bool {
+        self.hide_until.is_some_and(|until| now < until)
+    }
+
+    /// Set the status, completing the task or clearing its completion as needed
+    pub fn set_status(&mut self, status: StatusEnum, now: DateTime<Local>) {
+        if status == StatusEnum::Done {
+            if !self.done {
+                self.complete(now);
+            }
+            return;
+        }
+        self.done = false;
+        self.completed_at = None;
+        self.lead_time_seconds = None;
+        self.status = status;
+        self.mark_modified(now);
+    }
+
+    /// Align the status with `done`, which files written before the status existed only have
+    pub fn sync_status(&mut self) {
         if self.done {
-            println!(
-                "✅ {} - Created on {} - Completed on {}{}",
-                self.title,
-                self.created_at,
-                self.completed_at
-                    .map_or("Not completed".to_string(), |dt| dt.to_string()),
+            self.status = StatusEnum::Done;
+        } else if self.status == StatusEnum::Done {
+            self.status = StatusEnum::Todo;
+        }
+    }
+
+    /// Add then remove tags, keeping each tag once
+    pub fn retag(&mut self, add: &[String], remove: &[String]) {
+        for tag in add {
+            if !self.tags.contains(tag) {
+                self.tags.push(tag.clone());
+            }
+        }
+        self.tags.retain(|tag| !remove.contains(tag));
+    }
+
+    /// Add then remove links, keeping each link once
+    pub fn relink(&mut self, add: &[String], remove: &[String]) {
+        for link in add {
+            if !self.links.contains(link) {
+                self.links.push(link.clone());
+            }
+        }
+        self.links.retain(|link| !remove.contains(link));
+    }
+
+    /// Stop the running timer, if any, adding the elapsed time to the total
+    pub fn stop_timer(&mut self, now: DateTime<Local>) {
+        if let Some(started_at) = self.started_at.take() {
+            self.total_seconds += (now - started_at).num_seconds().max(0);
+        }
+    }
+
+    /// Next `count` occurrences of a recurring task, starting from its due date
+    pub fn forecast(&self, count: usize) -> Vec<DateTime<Local>> {
+        let Some(recurrence) = &self.recurrence else {
+            return vec![];
+        };
+        let mut dates = vec![];
+        let mut next = Some(self.due_at.unwrap_or(self.created_at));
+        while let Some(date) = next
+            && dates.len() < count
+        {
+            dates.push(date);
+            next = recurrence.next(date);
+        }
+        dates
+    }
+
+    /// Render every field of the task, one per line
+    pub fn render_details(&self) -> String {
+        let mut lines = vec![
+            format!("Id: {}", self.id),
+            format!("Title: {}", self.title),
+            format!("Status: {:?}", self.status),
+            format!("Created on: {}", self.created_at),
+        ];
+        if let Some(completed_at) = self.completed_at {
+            lines.push(format!("Completed on: {}", completed_at));
+        }
+        if let Some(lead_time) = self.lead_time_seconds {
+            lines.push(format!("Lead time: {}s", lead_time));
+        }
+        if let Some(priority) = &self.priority {
+            lines.push(format!("Priority: {:?}", priority));
+        }
+        if let Some(due_at) = self.due_at {
+            lines.push(format!("Due on: {}", due_at));
+        }
+        if let Some(modified_at) = self.modified_at {
+            lines.push(format!("Modified on: {}", modified_at));
+        }
+        if let Some(hide_until) = self.hide_until {
+            lines.push(format!("Hidden until: {}", hide_until));
+        }
+        if let Some(escalated_at) = self.escalated_at {
+            lines.push(format!("Escalated on: {}", escalated_at));
+        }
+        if let Some(parent_id) = self.parent_id {
+            lines.push(format!("Subtask of: {}", parent_id));
+        }
+        if let Some(remind_at) = self.remind_at {
+            lines.push(format!("Reminder on: {}", remind_at));
+        }
+        if let Some(recurrence) = &self.recurrence {
+            lines.push(format!("Repeats: {:?}", recurrence));
+        }
+        if let Some(description) = &self.description {
+            lines.push(format!("Description: {}", description));
+        }
+        if self.progress > 0 {
+            lines.push(format!("Progress: {}%", self.progress));
+        }
+        if self.total_seconds > 0 || self.started_at.is_some() {
+            lines.push(format!(
+                "Tracked: {}s{}",
+                self.total_seconds,
+                if self.started_at.is_some() {
+                    " (running)"
+                } else {
+                    ""
+                }
+            ));
+        }
+        if let Some(source) = &self.source {
+            lines.push(format!("Source: {}", source));
+        }
+        if !self.tags.is_empty() {
+            lines.push(format!("Tags: {}", self.tags.join(", ")));
+        }
+        if !self.links.is_empty() {
+            lines.push(format!("Links: {}", self.links.join(", ")));
+        }
+        if !self.title_history.is_empty() {
+            lines.push(format!(
+                "Previous titles: {}",
+                self.title_history.join(", ")
+            ));
+        }
+        lines.join("\n")
+    }
+
+    /// Render only the selected fields, in order
+    pub fn render_fields(&self, fields: &[FieldEnum]) -> String {
+        self.field_values(fields).join(" - ")
+    }
+
+    /// The value of each field, empty when unset
+    pub fn field_values(&self, fields: &[FieldEnum]) -> Vec<String> {
+        let optional = |value: Option<String>| value.unwrap_or_default();
+        fields
+            .iter()
+            .map(|field| match field {
+                FieldEnum::Id => self.id.to_string(),
+                FieldEnum::Title => self.title.clone(),
+                FieldEnum::Status => if self.done { "✅" } else { "❌" }.to_string(),
+                FieldEnum::Priority => optional(self.priority.as_ref().map(|p| format!("{:?}", p))),
+                FieldEnum::Due => optional(self.due_at.map(|dt| dt.to_string())),
+                FieldEnum::Created => self.created_at.to_string(),
+                FieldEnum::Completed => optional(self.completed_at.map(|dt| dt.to_string())),
+                FieldEnum::Description => optional(self.description.clone()),
+            })
+            .collect()
+    }
+
+    /// The color of the task's row when coloring by `by`, `None` leaving it uncolored
+    pub fn row_color(
+        &self,
+        by: ColorByEnum,
+        theme: &Theme,
+        now: DateTime<Local>,
+    ) -> Option<&'static str> {
+        match by {
+            ColorByEnum::Priority => match self.priority.as_ref()? {
+                PriorityEnum::High => Some(theme.high.code()),
+                PriorityEnum::Medium => Some(theme.medium.code()),
+                PriorityEnum::Low => Some(theme.low.code()),
+            },
+            ColorByEnum::Status => Some(match self.status {
+                StatusEnum::Todo => BLUE,
+                StatusEnum::Doing => YELLOW,
+                StatusEnum::Waiting => MAGENTA,
+                StatusEnum::Done => GREEN,
+            }),
+            ColorByEnum::Tag => self.tags.first().map(|tag| tag_color(tag)),
+            ColorByEnum::Due => {
+                let due = self.due_at.filter(|_| !self.done)?;
+                Some(if due < now {
+                    RED
+                } else if due - now <= chrono::Duration::days(DUE_SOON_DAYS) {
+                    YELLOW
+                } else {
+                    GREEN
+                })
+            }
+        }
+    }
+
+    /// Render the task, wrapping its description to `width` columns under the title, if given
+    pub fn render(
+        &self,
+        color: bool,
+        theme: &Theme,
+        timestamps: TimestampEnum,
+        id_width: usize,
+        width: Option<usize>,
+    ) -> String {
+        let priority = match self.priority {
+            Some(PriorityEnum::High) => paint(" - Priority high", theme.high.code(), color),
+            Some(PriorityEnum::Medium) => paint(" - Priority medium", theme.medium.code(), color),
+            Some(PriorityEnum::Low) => paint(" - Priority low", theme.low.code(), color),
+            None => String::new(),
+        };
+        let due = self.due_at.map_or(String::new(), |dt| {
+            format!(" - Due on {}", timestamps.format_due(dt))
+        });
+        let created = timestamps
+            .format(self.created_at)
+            .map_or(String::new(), |dt| format!(" - Created on {}", dt));
+        let status = match self.status {
+            StatusEnum::Doing => " - Doing",
+            StatusEnum::Waiting => " - Waiting",
+            StatusEnum::Todo | StatusEnum::Done => "",
+        };
+        let line = if self.done {
+            let completed = match self.completed_at {
+                Some(dt) => timestamps.format(dt),
+                None => Some("Not completed".to_string()),
+            };
+            format!(
+                "{:>width$} ✅ {}{}{}{}{}",
+                self.id,
+                paint(&self.title, theme.done.code(), color),
+                created,
+                completed.map_or(String::new(), |dt| format!(" - Completed on {}", dt)),
+                due,
                 priority,
-            );
+                width = id_width,
+            )
         } else {
-            println!(
-                "❌ {} - Created on {}{}",
-                self.title, self.created_at, priority
+            format!(
+                "{:>width$} ❌ {}{}{}{}{}",
+                self.id,
+                paint(&self.title, theme.pending.code(), color),
+                status,
+                created,
+                due,
+                priority,
+                width = id_width,
             )
+        };
+        match &self.description {
+            Some(description) => {
+                // Hang the description under the title, past the id and the status icon
+                let indent = " ".repeat(id_width + 4);
+                let wrapped: Vec<String> = match width {
+                    Some(width) => {
+                        let options = textwrap::Options::new(width)
+                            .initial_indent(&indent)
+                            .subsequent_indent(&indent);
+                        textwrap::wrap(description, options)
+                            .into_iter()
+                            .map(String::from)
+                            .collect()
+                    }
+                    None => description
+                        .lines()
+                        .map(|text| format!("{}{}", indent, text))
+                        .collect(),
+                };
+                format!("{}\n{}", line, wrapped.join("\n"))
+            }
+            None => line,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_builder_defaults() {
+        let task = TaskBuilder::new().title("task").build(3);
+        assert_eq!(task.id, 3);
+        assert_eq!(task.title, "task");
+        assert!(!task.done);
+        assert_eq!(task.status, StatusEnum::Todo);
+        assert_eq!(task.priority, None);
+        assert_eq!(task.due_at, None);
+        assert!(task.tags.is_empty());
+        assert!(task.completed_at.is_none());
+    }
+
+    #[test]
+    fn test_builder_fields() {
+        let due = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        let task = TaskBuilder::new()
+            .title("report")
+            .priority(PriorityEnum::High)
+            .due(due)
+            .tag("work")
+            .tag("urgent")
+            .tag("work")
+            .build(1);
+        assert_eq!(task.priority, Some(PriorityEnum::High));
+        assert_eq!(task.due_at, Some(due));
+        assert_eq!(task.tags, vec!["work", "urgent"]);
+
+        let task = TaskBuilder::new()
+            .title("chore")
+            .priority(Some(PriorityEnum::Low))
+            .priority(None)
+            .build(2);
+        assert_eq!(task.priority, None);
+    }
+
+    #[test]
+    fn test_builder_add_fields() {
+        let remind = Local.with_ymd_and_hms(2024, 6, 1, 9, 0, 0).unwrap();
+        let task = TaskBuilder::new()
+            .title("water plants")
+            .recurrence(RecurrenceEnum::Weekly)
+            .description("the balcony ones".to_string())
+            .source(Some("laptop".to_string()))
+            .remind(remind)
+            .parent(4)
+            .link("https://example.com/a")
+            .link("https://example.com/a")
+            .build(5);
+        assert_eq!(task.recurrence, Some(RecurrenceEnum::Weekly));
+        assert_eq!(task.description.as_deref(), Some("the balcony ones"));
+        assert_eq!(task.source.as_deref(), Some("laptop"));
+        assert_eq!(task.remind_at, Some(remind));
+        assert_eq!(task.parent_id, Some(4));
+        assert_eq!(task.links, vec!["https://example.com/a"]);
+        assert_eq!(task.due_at, None);
+    }
+
+    #[test]
+    fn test_relink_and_details() {
+        let mut task = Task::new(1, "fix login".to_string(), None);
+        let ticket = "https://example.com/issues/12".to_string();
+        let doc = "https://example.com/docs/login".to_string();
+        task.relink(&[ticket.clone(), doc.clone(), ticket.clone()], &[]);
+        assert_eq!(task.links, vec![ticket.clone(), doc.clone()]);
+        assert!(
+            task.render_details()
+                .contains(&format!("Links: {}, {}", ticket, doc))
+        );
+
+        task.relink(&[], &[ticket]);
+        assert_eq!(task.links, vec![doc]);
+
+        let json = serde_json::to_string(&task).unwrap();
+        let task: Task = serde_json::from_str(&json).unwrap();
+        assert_eq!(task.links, vec!["https://example.com/docs/login"]);
+    }
+}
@@ -1,324 +1,4699 @@
-use chrono::Local;
+use chrono::{DateTime, Days, Local, NaiveDate};
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 use std::{
-    fs,
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fmt, fs,
+    io::{self, BufRead, BufWriter, Write},
     path::{Path, PathBuf},
+    sync::Arc,
     vec,
 };
 
-use crate::task::Task;
+use crate::cli::DEFAULT_WIDTH;
+use crate::color::{ColorByEnum, Theme, paint};
+use crate::duration::parse_duration;
+use crate::filter::TaskFilter;
+use crate::importer;
+use crate::interrupt::Interrupt;
+use crate::prompt;
+use crate::stats::Stats;
+use crate::store::{self, BackendEnum, FileStore, MemoryStore, Store};
+use crate::task::{FieldEnum, SortEnum, StatusEnum, Task, TaskBuilder, TimestampEnum, hostname};
 use crate::{exporter::*, task::PriorityEnum};
 
+#[derive(Debug)]
+pub enum LoadError {
+    DeserializationError(String),
+    IoError(io::Error),
+    UnknownFields(Vec<String>),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::DeserializationError(msg) => write!(f, "Deserialization failed {}", msg),
+            LoadError::IoError(e) => write!(f, "IO error {}", e),
+            LoadError::UnknownFields(fields) => write!(f, "Unknown fields {}", fields.join(", ")),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    ReadOnly(PathBuf),
+    Interrupted,
+    Export(ExportError),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::ReadOnly(path) => {
+                write!(
+                    f,
+                    "{} could not be read, changes were not saved",
+                    path.display()
+                )
+            }
+            SaveError::Interrupted => write!(f, "Interrupted, changes were not saved"),
+            SaveError::Export(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ArchiveError {
+    Load(PathBuf, LoadError),
+    Save(SaveError),
+}
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArchiveError::Load(path, e) => {
+                write!(f, "Could not read the archive {} {}", path.display(), e)
+            }
+            ArchiveError::Save(e) => write!(f, "Could not write the archive {}", e),
+        }
+    }
+}
+
+/// Whether both paths name the same file, comparing them as given when either is missing
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+fn default_store() -> Box<dyn Store> {
+    Box::<MemoryStore>::default()
+}
+
+/// The fields of porcelain output when none are chosen
+const PORCELAIN_FIELDS: [FieldEnum; 5] = [
+    FieldEnum::Id,
+    FieldEnum::Status,
+    FieldEnum::Priority,
+    FieldEnum::Due,
+    FieldEnum::Title,
+];
+
+/// The JSON output of list commands, new fields being added without breaking parsers
+#[derive(Serialize, Debug)]
+pub struct JsonListing<'a> {
+    /// Raised only when existing fields change meaning
+    pub version: u32,
+    pub count: usize,
+    pub tasks: Vec<&'a Task>,
+}
+
+impl<'a> JsonListing<'a> {
+    pub fn new(tasks: Vec<&'a Task>) -> Self {
+        JsonListing {
+            version: 1,
+            count: tasks.len(),
+            tasks,
+        }
+    }
+}
+
+/// What became of a task during a review
+enum ReviewEnum {
+    Changed,
+    Skip,
+    Quit,
+}
+
+/// How list commands print the tasks
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub enum OutputEnum {
+    #[default]
+    Text,
+    /// A `JsonListing` object
+    Json,
+    JsonPretty,
+    /// The bare array of tasks
+    JsonArray,
+    /// One line per task, fields split by the separator
+    Porcelain,
+    /// Like porcelain, each task ending with a NUL byte instead of a newline
+    Null,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct TodoList {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
     pub tasks: Vec<Task>,
     #[serde(skip)]
     pub path: PathBuf,
+    #[serde(skip)]
+    pub color: bool,
+    /// Columns to wrap descriptions to, `None` to leave them unwrapped
+    #[serde(skip)]
+    pub width: Option<usize>,
+    #[serde(skip)]
+    pub theme: Theme,
+    /// Color whole rows by this instead of the titles and priorities, when color is on
+    #[serde(skip)]
+    pub color_by: Option<ColorByEnum>,
+    /// How precisely the list and the CSV and Markdown exports show dates
+    #[serde(skip)]
+    pub timestamps: TimestampEnum,
+    #[serde(skip)]
+    pub fields: Vec<FieldEnum>,
+    #[serde(skip)]
+    pub interrupt: Arc<Interrupt>,
+    /// Set when the save file exists but could not be read, so it is never overwritten
+    #[serde(skip)]
+    pub read_only: bool,
+    #[serde(skip)]
+    pub sort: Option<SortEnum>,
+    #[serde(skip)]
+    pub none_first: bool,
+    /// Show at most this many tasks, after filtering and sorting
+    #[serde(skip)]
+    pub limit: Option<usize>,
+    /// List subtasks in place instead of under their parent
+    #[serde(skip)]
+    pub flat: bool,
+    /// Show only the tasks matching this filter
+    #[serde(skip)]
+    pub filter: TaskFilter,
+    #[serde(skip)]
+    pub output: OutputEnum,
+    /// Between the fields of porcelain output
+    #[serde(skip)]
+    pub separator: String,
+    /// Write listings to this file instead of stdout
+    #[serde(skip)]
+    pub output_path: Option<PathBuf>,
+    #[serde(skip)]
+    pub file_mode: Option<u32>,
+    /// Keep only this many completed tasks, the most recently completed, pruning the rest on save
+    #[serde(skip)]
+    pub max_completed: Option<usize>,
+    /// Move the pruned tasks to the archive file instead of deleting them
+    #[serde(skip)]
+    pub archive_pruned: bool,
+    /// Append completed tasks to the day's `done-YYYY-MM-DD.md` next to the save file
+    #[serde(skip)]
+    pub journal: bool,
+    /// Complete a parent too once its last pending subtask is completed
+    #[serde(skip)]
+    pub cascade_parent: bool,
+    /// Where the list is saved, replaced by the store it was loaded from
+    #[serde(skip, default = "default_store")]
+    pub store: Box<dyn Store>,
 }
 
 impl TodoList {
     pub fn new(path: &Path) -> Self {
-        let todolist = TodoList {
+        let mut todolist = TodoList::empty(path);
+        todolist.save_tasks();
+        todolist
+    }
+
+    /// An empty list for `path`, without writing it
+    pub fn empty(path: &Path) -> Self {
+        TodoList {
+            name: None,
+            description: None,
             tasks: vec![],
             path: path.to_path_buf(),
+            color: false,
+            width: Some(DEFAULT_WIDTH),
+            theme: Theme::default(),
+            color_by: None,
+            timestamps: TimestampEnum::Datetime,
+            fields: vec![],
+            interrupt: Arc::default(),
+            read_only: false,
+            sort: None,
+            none_first: false,
+            limit: None,
+            flat: false,
+            filter: TaskFilter::new(),
+            output: OutputEnum::Text,
+            separator: "\t".to_string(),
+            output_path: None,
+            file_mode: None,
+            max_completed: None,
+            archive_pruned: false,
+            journal: false,
+            cascade_parent: false,
+            store: Box::new(FileStore::new(path.to_path_buf())),
+        }
+    }
+
+    pub fn next_id(&self) -> i32 {
+        self.tasks.last().map_or(1, |task| task.id + 1)
+    }
+
+    pub fn add_task(&mut self, title: String, priority: Option<PriorityEnum>) {
+        self.add(TaskBuilder::new().title(title).priority(priority));
+    }
+
+    /// Build the task with the next id and add it, returning the id
+    pub fn add(&mut self, builder: TaskBuilder) -> i32 {
+        let task = builder.build(self.next_id());
+        let id = task.id;
+        self.insert_task(task);
+        id
+    }
+
+    /// Build several tasks under the next ids and add them, saving once, returning the ids
+    pub fn add_all(&mut self, builders: impl IntoIterator<Item = TaskBuilder>) -> Vec<i32> {
+        let ids = builders
+            .into_iter()
+            .map(|builder| {
+                let task = builder.build(self.next_id());
+                let id = task.id;
+                self.push_task(task);
+                id
+            })
+            .collect();
+        self.save_tasks();
+        ids
+    }
+
+    pub fn insert_task(&mut self, task: Task) {
+        self.push_task(task);
+        self.save_tasks();
+    }
+
+    fn push_task(&mut self, task: Task) {
+        self.log_event(Local::now(), &format!("add {} {}", task.id, task.title));
+        self.tasks.push(task);
+    }
+
+    /// Remove a task, returning false without saving when there is no such task
+    pub fn remove_task(&mut self, id: i32) -> bool {
+        let Some(index) = self.tasks.iter().position(|task| task.id == id) else {
+            eprintln!("No task with id {}", id);
+            return false;
         };
-        todolist.save_tasks();
-        todolist
+        let task = self.tasks.remove(index);
+        self.log_event(Local::now(), &format!("remove {} {}", task.id, task.title));
+        self.save_tasks();
+        true
+    }
+
+    /// Append a line to the event log, which is never rewritten
+    fn log_event(&self, now: DateTime<Local>, event: &str) {
+        if self.read_only {
+            return;
+        }
+        if let Err(e) = self
+            .store
+            .append_log(self, &format!("{} {}", now.to_rfc3339(), event))
+        {
+            eprintln!("IO error {}", e);
+        }
+    }
+
+    /// The event log lines, oldest first
+    pub fn history(&self) -> io::Result<Vec<String>> {
+        self.store.read_log()
+    }
+
+    /// Pending tasks sharing a trimmed, lowercased title, oldest first in each group
+    pub fn duplicate_groups(&self) -> Vec<Vec<&Task>> {
+        let mut groups: Vec<(String, Vec<&Task>)> = vec![];
+        for task in self.pending_tasks() {
+            let key = task.title.trim().to_lowercase();
+            match groups.iter_mut().find(|(title, _)| *title == key) {
+                Some((_, group)) => group.push(task),
+                None => groups.push((key, vec![task])),
+            }
+        }
+        groups
+            .into_iter()
+            .map(|(_, mut group)| {
+                group.sort_by_key(|task| (task.created_at, task.id));
+                group
+            })
+            .filter(|group| group.len() > 1)
+            .collect()
+    }
+
+    /// Remove all but the oldest task of each duplicate group
+    pub fn remove_duplicates(&mut self) -> usize {
+        let ids: HashSet<i32> = self
+            .duplicate_groups()
+            .iter()
+            .flat_map(|group| group.iter().skip(1).map(|task| task.id))
+            .collect();
+        let (removed, kept): (Vec<Task>, Vec<Task>) = std::mem::take(&mut self.tasks)
+            .into_iter()
+            .partition(|task| ids.contains(&task.id));
+        self.tasks = kept;
+        let now = Local::now();
+        for task in removed.iter() {
+            self.log_event(now, &format!("remove {} {}", task.id, task.title));
+        }
+        self.save_tasks();
+        removed.len()
+    }
+
+    pub fn display_duplicates(&self) {
+        for group in self.duplicate_groups() {
+            for task in group {
+                println!(
+                    "{}",
+                    task.render(self.color, &self.theme, self.timestamps, 0, self.width)
+                );
+            }
+            println!();
+        }
+    }
+
+    pub fn list_tasks(&self) {
+        self.display_tasks(self.tasks.iter());
+    }
+
+    pub fn list_completed_tasks(&self) {
+        self.display_tasks(self.completed_tasks());
+    }
+
+    pub fn list_pending_tasks(&self) {
+        self.display_tasks(self.pending_tasks());
+    }
+
+    /// The list name and description, followed by a blank line, when set
+    fn header(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .name
+            .iter()
+            .chain(self.description.iter())
+            .cloned()
+            .collect();
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines
+    }
+
+    pub fn set_name(&mut self, name: String, description: Option<String>) {
+        self.name = Some(name);
+        if description.is_some() {
+            self.description = description;
+        }
+        self.save_tasks();
+    }
+
+    /// Render one line per task, with ids padded to the widest id of the view
+    pub fn render_tasks<'a>(&self, tasks: impl Iterator<Item = &'a Task>) -> Vec<String> {
+        self.render_view(self.view_tasks(tasks))
+    }
+
+    fn render_view(&self, tasks: Vec<&Task>) -> Vec<String> {
+        let id_width = tasks
+            .iter()
+            .map(|task| task.id.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let nested = if self.flat {
+            tasks.into_iter().map(|task| (task, 0)).collect()
+        } else {
+            Self::nest_tasks(&tasks)
+        };
+        let color_by = self.color_by.filter(|_| self.color);
+        let now = Local::now();
+        nested
+            .into_iter()
+            .map(|(task, depth)| {
+                let line = if self.fields.is_empty() {
+                    let color = self.color && color_by.is_none();
+                    task.render(color, &self.theme, self.timestamps, id_width, self.width)
+                } else {
+                    task.render_fields(&self.fields)
+                };
+                let row_color = color_by.and_then(|by| task.row_color(by, &self.theme, now));
+                let indent = "  ".repeat(depth);
+                line.lines()
+                    .map(|line| match row_color {
+                        Some(code) => format!("{}{}", indent, paint(line, code, true)),
+                        None => format!("{}{}", indent, line),
+                    })
+                    .collect::<Vec<String>>()
+                    .join("\n")
+            })
+            .collect()
+    }
+
+    /// The tasks to show once filtered and sorted
+    pub fn view_tasks<'a>(&self, tasks: impl Iterator<Item = &'a Task>) -> Vec<&'a Task> {
+        self.view_tasks_counted(tasks).0
+    }
+
+    /// The view along with how many tasks the limit left out of it
+    fn view_tasks_counted<'a>(
+        &self,
+        tasks: impl Iterator<Item = &'a Task>,
+    ) -> (Vec<&'a Task>, usize) {
+        let mut tasks: Vec<&Task> = tasks.filter(|task| self.filter.matches(task)).collect();
+        if let Some(sort) = self.sort {
+            self.sort_tasks(&mut tasks, sort);
+        }
+        let more = match self.limit {
+            Some(limit) if tasks.len() > limit => tasks.len() - limit,
+            _ => 0,
+        };
+        tasks.truncate(tasks.len() - more);
+        (tasks, more)
+    }
+
+    /// Order the tasks depth first, each subtask under its parent when the parent is in view
+    pub fn nest_tasks<'a>(tasks: &[&'a Task]) -> Vec<(&'a Task, usize)> {
+        let ids: HashSet<i32> = tasks.iter().map(|task| task.id).collect();
+        let mut visited = HashSet::new();
+        let mut nested = vec![];
+        let roots = tasks.iter().filter(|task| {
+            task.parent_id
+                .is_none_or(|parent_id| !ids.contains(&parent_id))
+        });
+        for task in roots {
+            Self::push_nested(tasks, task, 0, &mut visited, &mut nested);
+        }
+        // Tasks in a parent cycle are never reached from a root, show them at the top level
+        for task in tasks.iter() {
+            Self::push_nested(tasks, task, 0, &mut visited, &mut nested);
+        }
+        nested
+    }
+
+    fn push_nested<'a>(
+        tasks: &[&'a Task],
+        task: &'a Task,
+        depth: usize,
+        visited: &mut HashSet<i32>,
+        nested: &mut Vec<(&'a Task, usize)>,
+    ) {
+        if !visited.insert(task.id) {
+            return;
+        }
+        nested.push((task, depth));
+        for child in tasks
+            .iter()
+            .filter(|child| child.parent_id == Some(task.id))
+        {
+            Self::push_nested(tasks, child, depth + 1, visited, nested);
+        }
+    }
+
+    /// Stable sort, tasks without a priority or due date going last (or first for priority with `none_first`)
+    ///
+    /// Dates are saved to the nanosecond, and tasks with the same date are ordered by id so the
+    /// time-based sorts do not depend on the order of the list.
+    fn sort_tasks(&self, tasks: &mut [&Task], sort: SortEnum) {
+        match sort {
+            SortEnum::Priority => tasks.sort_by(|a, b| match (&a.priority, &b.priority) {
+                (Some(a), Some(b)) => a.cmp(b),
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) if self.none_first => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) if self.none_first => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+            }),
+            SortEnum::Due => {
+                tasks.sort_by_key(|task| (task.due_at.is_none(), task.due_at, task.id))
+            }
+            SortEnum::Created => tasks.sort_by_key(|task| (task.created_at, task.id)),
+        }
+    }
+
+    /// A copy of the list with its tasks by id, then in `sort` order, for exports
+    ///
+    /// Exports never follow the order the tasks are kept in, so they diff cleanly.
+    fn sorted_copy(&self, sort: Option<SortEnum>) -> TodoList {
+        let mut tasks: Vec<&Task> = self.tasks.iter().collect();
+        tasks.sort_by_key(|task| task.id);
+        if let Some(sort) = sort {
+            self.sort_tasks(&mut tasks, sort);
+        }
+        let mut sorted = TodoList::empty(&self.path);
+        sorted.timestamps = self.timestamps;
+        sorted.name = self.name.clone();
+        sorted.description = self.description.clone();
+        sorted.tasks = tasks.into_iter().cloned().collect();
+        sorted
+    }
+
+    pub fn write_tasks<'a>(
+        &self,
+        out: &mut impl Write,
+        tasks: impl Iterator<Item = &'a Task>,
+    ) -> io::Result<()> {
+        match self.output {
+            OutputEnum::Text => {
+                for line in self.header() {
+                    writeln!(out, "{}", line)?;
+                }
+                let (tasks, more) = self.view_tasks_counted(tasks);
+                for line in self.render_view(tasks) {
+                    writeln!(out, "{}", line)?;
+                }
+                if more > 0 {
+                    writeln!(out, "...and {} more", more)?;
+                }
+            }
+            OutputEnum::Json => {
+                serde_json::to_writer(&mut *out, &JsonListing::new(self.view_tasks(tasks)))?;
+                writeln!(out)?;
+            }
+            OutputEnum::JsonPretty => {
+                let listing = JsonListing::new(self.view_tasks(tasks));
+                serde_json::to_writer_pretty(&mut *out, &listing)?;
+                writeln!(out)?;
+            }
+            OutputEnum::JsonArray => {
+                serde_json::to_writer(&mut *out, &self.view_tasks(tasks))?;
+                writeln!(out)?;
+            }
+            OutputEnum::Porcelain | OutputEnum::Null => {
+                let fields = if self.fields.is_empty() {
+                    &PORCELAIN_FIELDS[..]
+                } else {
+                    &self.fields[..]
+                };
+                let end = if self.output == OutputEnum::Null {
+                    '\0'
+                } else {
+                    '\n'
+                };
+                for task in self.view_tasks(tasks) {
+                    let values = task.field_values(fields);
+                    write!(out, "{}{}", values.join(&self.separator), end)?;
+                }
+            }
+        }
+        out.flush()
+    }
+
+    /// Print the tasks in one buffered pass rather than locking stdout for each line
+    fn display_tasks<'a>(&self, tasks: impl Iterator<Item = &'a Task>) {
+        let written = match &self.output_path {
+            Some(path) => self.write_tasks_to(path, tasks),
+            None => self.write_tasks(&mut BufWriter::new(io::stdout().lock()), tasks),
+        };
+        if let Err(e) = written {
+            eprintln!("IO error {}", e);
+        }
+    }
+
+    /// Write the listing to a file, replacing it and creating its parent directories
+    fn write_tasks_to<'a>(
+        &self,
+        path: &Path,
+        tasks: impl Iterator<Item = &'a Task>,
+    ) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut out = BufWriter::new(fs::File::create(path)?);
+        self.write_tasks(&mut out, tasks)?;
+        out.flush()
+    }
+
+    pub fn complete_task(&mut self, id: i32) {
+        self.complete_tasks_at(&[id], Local::now());
+    }
+
+    pub fn complete_task_at(&mut self, id: i32, now: DateTime<Local>) {
+        self.complete_tasks_at(&[id], now);
+    }
+
+    /// Complete several tasks, saving once for all of them, returning false if any is unknown
+    ///
+    /// Nothing is saved when none of them exists.
+    pub fn complete_tasks(&mut self, ids: &[i32]) -> bool {
+        self.complete_tasks_at(ids, Local::now())
+    }
+
+    pub fn complete_tasks_at(&mut self, ids: &[i32], now: DateTime<Local>) -> bool {
+        let missing = self.mark_completed(ids, now);
+        if missing.len() < ids.len() {
+            self.save_tasks();
+        }
+        missing.is_empty()
+    }
+
+    /// Complete `id` and reopen `wrong`, completed by mistake instead, saving both at once
+    ///
+    /// Nothing changes unless both tasks exist and `wrong` is completed. Returns whether it did.
+    pub fn complete_instead_of(&mut self, id: i32, wrong: i32) -> bool {
+        self.complete_instead_of_at(id, wrong, Local::now())
+    }
+
+    pub fn complete_instead_of_at(&mut self, id: i32, wrong: i32, now: DateTime<Local>) -> bool {
+        if let Some(missing) = [id, wrong]
+            .into_iter()
+            .find(|&id| !self.tasks.iter().any(|task| task.id == id))
+        {
+            eprintln!("No task with id {}", missing);
+            return false;
+        }
+        if id == wrong {
+            eprintln!("Task {} cannot be completed instead of itself", id);
+            return false;
+        }
+        let Some(task) = self
+            .tasks
+            .iter_mut()
+            .find(|task| task.id == wrong && task.done)
+        else {
+            eprintln!("Task {} is not completed", wrong);
+            return false;
+        };
+        task.set_status(StatusEnum::Todo, now);
+        let event = format!("reopen {} {}", task.id, task.title);
+        self.log_event(now, &event);
+        self.mark_completed(&[id], now);
+        self.save_tasks();
+        true
+    }
+
+    /// Complete tasks then remove them, the log keeping both events, saving once
+    pub fn complete_and_remove_tasks(&mut self, ids: &[i32]) -> bool {
+        self.complete_and_remove_tasks_at(ids, Local::now())
+    }
+
+    pub fn complete_and_remove_tasks_at(&mut self, ids: &[i32], now: DateTime<Local>) -> bool {
+        let missing = self.mark_completed(ids, now);
+        if missing.len() == ids.len() {
+            return missing.is_empty();
+        }
+        for &id in ids {
+            if let Some(index) = self.tasks.iter().position(|task| task.id == id) {
+                let task = self.tasks.remove(index);
+                self.log_event(now, &format!("remove {} {}", task.id, task.title));
+            }
+        }
+        self.save_tasks();
+        missing.is_empty()
+    }
+
+    /// Complete the tasks, and their parents with `cascade_parent`, returning the unknown ids
+    fn mark_completed(&mut self, ids: &[i32], now: DateTime<Local>) -> Vec<i32> {
+        let mut missing = vec![];
+        let mut ids = ids.to_vec();
+        let mut index = 0;
+        while let Some(&id) = ids.get(index) {
+            index += 1;
+            let parent_id = match self.tasks.iter_mut().find(|task| task.id == id) {
+                Some(task) => {
+                    task.complete(now);
+                    let event = format!("complete {} {}", task.id, task.title);
+                    let entry = format!("- {} {}", now.format("%H:%M"), task.title);
+                    let parent_id = task.parent_id;
+                    self.log_event(now, &event);
+                    if self.journal {
+                        self.write_journal(now.date_naive(), &entry);
+                    }
+                    parent_id
+                }
+                None => {
+                    eprintln!("No task with id {}", id);
+                    missing.push(id);
+                    continue;
+                }
+            };
+            // Walk up one level per completed parent, reaching the grandparent in turn
+            if self.cascade_parent
+                && let Some(parent_id) = parent_id
+                && !ids.contains(&parent_id)
+                && self
+                    .tasks
+                    .iter()
+                    .any(|task| task.id == parent_id && !task.done)
+                && self.pending_subtasks(parent_id).is_empty()
+            {
+                ids.push(parent_id);
+            }
+        }
+        missing
+    }
+
+    /// The journal of the tasks completed on `date`
+    pub fn journal_path(&self, date: NaiveDate) -> PathBuf {
+        self.path
+            .with_file_name(format!("done-{}.md", date.format("%Y-%m-%d")))
+    }
+
+    fn write_journal(&self, date: NaiveDate, entry: &str) {
+        if let Err(e) = store::open_append(&self.journal_path(date), self.file_mode)
+            .and_then(|mut file| writeln!(file, "{}", entry))
+        {
+            eprintln!("IO error {}", e);
+        }
+    }
+
+    /// The pending subtasks of a task, their own subtasks included
+    pub fn pending_subtasks(&self, id: i32) -> Vec<&Task> {
+        let mut parents = vec![id];
+        let mut seen = HashSet::from([id]);
+        let mut subtasks = vec![];
+        while let Some(parent) = parents.pop() {
+            for task in self.tasks.iter() {
+                if task.parent_id == Some(parent) && seen.insert(task.id) {
+                    parents.push(task.id);
+                    if !task.done {
+                        subtasks.push(task);
+                    }
+                }
+            }
+        }
+        subtasks
+    }
+
+    /// The ids to complete, asking first for those with pending subtasks unless `yes`
+    pub fn confirm_completion(&self, ids: &[i32], yes: bool, input: &mut impl BufRead) -> Vec<i32> {
+        ids.iter()
+            .copied()
+            .filter(|&id| {
+                let subtasks = self.pending_subtasks(id);
+                if yes || subtasks.is_empty() {
+                    return true;
+                }
+                println!("Task {} has pending subtasks:", id);
+                for line in self.render_tasks(subtasks.into_iter()) {
+                    println!("{}", line);
+                }
+                prompt::confirm(&format!("Complete task {} anyway?", id), input)
+            })
+            .collect()
+    }
+
+    /// Complete every pending task once confirmed, returning how many were completed
+    pub fn complete_all_tasks(&mut self, yes: bool, input: &mut impl BufRead) -> usize {
+        let ids: Vec<i32> = self.pending_tasks().map(|task| task.id).collect();
+        let count = ids.len();
+        if count == 0
+            || !yes && !prompt::confirm(&format!("Complete {} pending tasks?", count), input)
+        {
+            return 0;
+        }
+        self.complete_tasks(&ids);
+        println!("Completed {} tasks", count);
+        count
+    }
+
+    /// Delete every task once the list name, or `PURGE`, is typed back, returning how many went
+    pub fn purge_tasks(&mut self, yes: bool, input: &mut impl BufRead) -> usize {
+        let count = self.tasks.len();
+        if count == 0 {
+            return 0;
+        }
+        let mut expected = vec!["PURGE"];
+        expected.extend(self.name.as_deref());
+        if !yes
+            && !prompt::confirm_typed(
+                &format!(
+                    "Delete all {} tasks? Type {} to confirm:",
+                    count,
+                    expected.join(" or ")
+                ),
+                &expected,
+                input,
+            )
+        {
+            println!("Aborted");
+            return 0;
+        }
+        let now = Local::now();
+        for task in std::mem::take(&mut self.tasks) {
+            self.log_event(now, &format!("purge {} {}", task.id, task.title));
+        }
+        self.save_tasks();
+        println!("Removed {} tasks", count);
+        count
+    }
+
+    /// Step through the pending tasks, asking what to do with each, returning how many were changed
+    pub fn review_tasks(&mut self, input: &mut impl BufRead, now: DateTime<Local>) -> usize {
+        let ids: Vec<i32> = self.pending_tasks().map(|task| task.id).collect();
+        let mut changed = 0;
+        for id in ids {
+            let Some(task) = self.tasks.iter().find(|task| task.id == id) else {
+                continue;
+            };
+            self.display_task(task);
+            match self.review_task(id, input, now) {
+                Some(ReviewEnum::Changed) => changed += 1,
+                Some(ReviewEnum::Skip) => {}
+                Some(ReviewEnum::Quit) | None => break,
+            }
+        }
+        println!("Reviewed {} tasks", changed);
+        changed
+    }
+
+    fn review_task(
+        &mut self,
+        id: i32,
+        input: &mut impl BufRead,
+        now: DateTime<Local>,
+    ) -> Option<ReviewEnum> {
+        loop {
+            let answer = prompt::ask(
+                "[c]omplete, [r]emove, [p]riority, [s]nooze, s[k]ip or [q]uit?",
+                input,
+            )?;
+            match answer.to_lowercase().as_str() {
+                "c" | "complete" => {
+                    self.complete_task_at(id, now);
+                    return Some(ReviewEnum::Changed);
+                }
+                "r" | "remove" => {
+                    self.remove_task(id);
+                    return Some(ReviewEnum::Changed);
+                }
+                "p" | "priority" => {
+                    let answer = prompt::ask("Priority (high, medium, low)?", input)?;
+                    match PriorityEnum::from_str(&answer, true) {
+                        Ok(priority) => {
+                            self.update_task(id, |task| task.priority = Some(priority));
+                            return Some(ReviewEnum::Changed);
+                        }
+                        Err(_) => eprintln!("Unknown priority {}", answer),
+                    }
+                }
+                "s" | "snooze" => {
+                    let answer = prompt::ask("Snooze for (e.g. 3d)?", input)?;
+                    match parse_duration(&answer) {
+                        Ok(duration) => {
+                            self.update_task(id, |task| task.due_at = Some(now + duration));
+                            return Some(ReviewEnum::Changed);
+                        }
+                        Err(e) => eprintln!("Invalid duration {}", e),
+                    }
+                }
+                "k" | "skip" | "" => return Some(ReviewEnum::Skip),
+                "q" | "quit" => return Some(ReviewEnum::Quit),
+                _ => eprintln!("Unknown action {}", answer),
+            }
+        }
+    }
+
+    /// Apply `change` to a task and save
+    fn update_task(&mut self, id: i32, change: impl FnOnce(&mut Task)) {
+        let Some(task) = self.tasks.iter_mut().find(|task| task.id == id) else {
+            eprintln!("No task with id {}", id);
+            return;
+        };
+        change(task);
+        task.mark_modified(Local::now());
+        self.save_tasks();
+    }
+
+    /// Break a task down into subtasks, one per title
+    pub fn split_task(&mut self, id: i32, titles: Vec<String>) {
+        if !self.tasks.iter().any(|task| task.id == id) {
+            eprintln!("No task with id {}", id);
+            return;
+        }
+        self.add_all(titles.into_iter().map(|title| {
+            TaskBuilder::new()
+                .title(title)
+                .parent(id)
+                .source(hostname())
+        }));
+    }
+
+    /// Rename a task, keeping its previous title in its history
+    pub fn edit_task(&mut self, id: i32, title: String) {
+        let Some(task) = self.tasks.iter_mut().find(|task| task.id == id) else {
+            eprintln!("No task with id {}", id);
+            return;
+        };
+        let now = Local::now();
+        let event = format!("edit {} {} -> {}", id, task.title, title);
+        let previous = std::mem::replace(&mut task.title, title);
+        task.title_history.push(previous);
+        task.mark_modified(now);
+        self.log_event(now, &event);
+        self.save_tasks();
+    }
+
+    /// Bump the creation date of a task to now, leaving everything else untouched
+    pub fn touch_task(&mut self, id: i32) {
+        let Some(task) = self.tasks.iter_mut().find(|task| task.id == id) else {
+            eprintln!("No task with id {}", id);
+            return;
+        };
+        task.created_at = Local::now();
+        task.mark_modified(task.created_at);
+        self.save_tasks();
+    }
+
+    pub fn set_status(&mut self, id: i32, status: StatusEnum) {
+        let Some(task) = self.tasks.iter_mut().find(|task| task.id == id) else {
+            eprintln!("No task with id {}", id);
+            return;
+        };
+        task.set_status(status, Local::now());
+        self.save_tasks();
+    }
+
+    /// Add and remove tags on a task, returning its resulting tags
+    pub fn tag_task(&mut self, id: i32, add: &[String], remove: &[String]) -> Option<Vec<String>> {
+        let Some(task) = self.tasks.iter_mut().find(|task| task.id == id) else {
+            eprintln!("No task with id {}", id);
+            return None;
+        };
+        task.retag(add, remove);
+        task.mark_modified(Local::now());
+        let tags = task.tags.clone();
+        self.save_tasks();
+        Some(tags)
+    }
+
+    /// Hide a task from `list` until `until`, `None` showing it again
+    pub fn defer_task(&mut self, id: i32, until: Option<DateTime<Local>>) {
+        let Some(task) = self.tasks.iter_mut().find(|task| task.id == id) else {
+            eprintln!("No task with id {}", id);
+            return;
+        };
+        task.hide_until = until;
+        task.mark_modified(Local::now());
+        self.save_tasks();
+    }
+
+    /// Add and remove links on a task, returning its resulting links
+    pub fn link_task(&mut self, id: i32, add: &[String], remove: &[String]) -> Option<Vec<String>> {
+        let Some(task) = self.tasks.iter_mut().find(|task| task.id == id) else {
+            eprintln!("No task with id {}", id);
+            return None;
+        };
+        task.relink(add, remove);
+        task.mark_modified(Local::now());
+        let links = task.links.clone();
+        self.save_tasks();
+        Some(links)
+    }
+
+    /// A pending task chosen with `rng`, uniformly or, when `weighted`, three times as likely
+    /// for high priority and twice for medium as for low or no priority
+    pub fn pick_task(&self, rng: &mut fastrand::Rng, weighted: bool) -> Option<&Task> {
+        let pending: Vec<&Task> = self.pending_tasks().collect();
+        if !weighted {
+            return rng.choice(pending);
+        }
+        let weight = |task: &Task| match task.priority {
+            Some(PriorityEnum::High) => 3,
+            Some(PriorityEnum::Medium) => 2,
+            Some(PriorityEnum::Low) | None => 1,
+        };
+        let total: u32 = pending.iter().map(|task| weight(task)).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut draw = rng.u32(0..total);
+        pending.into_iter().find(|task| {
+            let weight = weight(task);
+            if draw < weight {
+                return true;
+            }
+            draw -= weight;
+            false
+        })
+    }
+
+    pub fn display_task(&self, task: &Task) {
+        println!(
+            "{}",
+            task.render(self.color, &self.theme, self.timestamps, 0, self.width)
+        );
+    }
+
+    pub fn show_task(&self, id: i32) {
+        match self.tasks.iter().find(|task| task.id == id) {
+            Some(task) => println!("{}", task.render_details()),
+            None => eprintln!("No task with id {}", id),
+        }
+    }
+
+    pub fn set_progress(&mut self, id: i32, percent: u8) {
+        let Some(task) = self.tasks.iter_mut().find(|task| task.id == id) else {
+            eprintln!("No task with id {}", id);
+            return;
+        };
+        task.progress = percent.min(100);
+        task.mark_modified(Local::now());
+        self.save_tasks();
+    }
+
+    /// Raise the progress of a task, capped at 100%, which completes it
+    pub fn add_progress(&mut self, id: i32, percent: u8) {
+        self.add_progress_at(id, percent, Local::now());
+    }
+
+    pub fn add_progress_at(&mut self, id: i32, percent: u8, now: DateTime<Local>) {
+        let Some(task) = self.tasks.iter_mut().find(|task| task.id == id) else {
+            eprintln!("No task with id {}", id);
+            return;
+        };
+        task.progress = task.progress.saturating_add(percent).min(100);
+        task.mark_modified(now);
+        if task.progress == 100 && !task.done {
+            self.mark_completed(&[id], now);
+        }
+        self.save_tasks();
+    }
+
+    pub fn start_task(&mut self, id: i32) {
+        let Some(task) = self.tasks.iter_mut().find(|task| task.id == id) else {
+            eprintln!("No task with id {}", id);
+            return;
+        };
+        if task.started_at.is_some() {
+            eprintln!("Task {} is already started", id);
+            return;
+        }
+        let now = Local::now();
+        task.started_at = Some(now);
+        task.mark_modified(now);
+        self.save_tasks();
+    }
+
+    pub fn stop_task(&mut self, id: i32) {
+        let Some(task) = self.tasks.iter_mut().find(|task| task.id == id) else {
+            eprintln!("No task with id {}", id);
+            return;
+        };
+        let now = Local::now();
+        task.stop_timer(now);
+        task.mark_modified(now);
+        self.save_tasks();
+    }
+
+    pub fn forecast_task(&self, id: i32, count: usize) {
+        match self.tasks.iter().find(|task| task.id == id) {
+            Some(task) if task.recurrence.is_some() => {
+                for date in task.forecast(count) {
+                    println!("{}", date);
+                }
+            }
+            Some(_) => eprintln!("Task {} is not recurring", id),
+            None => eprintln!("No task with id {}", id),
+        }
+    }
+
+    /// Number of open tasks at the end of each of the last `days` days, oldest first
+    pub fn burndown(&self, days: u32, today: NaiveDate) -> Vec<(NaiveDate, usize)> {
+        (0..days)
+            .rev()
+            .filter_map(|offset| today.checked_sub_days(Days::new(offset.into())))
+            .map(|day| {
+                let open = self
+                    .tasks
+                    .iter()
+                    .filter(|task| task.created_at.date_naive() <= day)
+                    .filter(|task| {
+                        task.completed_at
+                            .is_none_or(|completed| completed.date_naive() > day)
+                    })
+                    .count();
+                (day, open)
+            })
+            .collect()
+    }
+
+    pub fn display_burndown(&self, days: u32) {
+        for (day, open) in self.burndown(days, Local::now().date_naive()) {
+            println!("{} | {} {}", day, "#".repeat(open), open);
+        }
+    }
+
+    /// Tasks completed on each of the last `days` local days, oldest first
+    pub fn completions_by_day(&self, days: u32, today: NaiveDate) -> Vec<(NaiveDate, usize)> {
+        (0..days)
+            .rev()
+            .filter_map(|offset| today.checked_sub_days(Days::new(offset.into())))
+            .map(|day| {
+                let done = self
+                    .completed_tasks()
+                    .filter(|task| {
+                        task.completed_at
+                            .is_some_and(|completed| completed.date_naive() == day)
+                    })
+                    .count();
+                (day, done)
+            })
+            .collect()
+    }
+
+    pub fn display_completions_by_day(&self, days: u32) {
+        for (day, done) in self.completions_by_day(days, Local::now().date_naive()) {
+            println!("{} | {} {}", day, "#".repeat(done), done);
+        }
+    }
+
+    /// Banners for the pending tasks whose reminder time has passed
+    pub fn reminders(&self, now: DateTime<Local>) -> Vec<String> {
+        self.pending_tasks()
+            .filter(|task| task.remind_at.is_some_and(|remind_at| remind_at <= now))
+            .map(|task| format!("⏰ Reminder: {}", task.title))
+            .collect()
+    }
+
+    /// Compact `✓done ◷pending ⚠overdue` summary, meant for shell prompts
+    pub fn status_line(&self, now: DateTime<Local>) -> String {
+        let done = self.completed_tasks().count();
+        let pending = self.pending_tasks().count();
+        let overdue = self
+            .pending_tasks()
+            .filter(|task| task.due_at.is_some_and(|due| due < now))
+            .count();
+        format!("✓{} ◷{} ⚠{}", done, pending, overdue)
+    }
+
+    pub fn write_status(&self, out: &mut impl Write, prompt: bool) -> io::Result<()> {
+        let line = self.status_line(Local::now());
+        if prompt {
+            write!(out, "{}", line)
+        } else {
+            writeln!(out, "{}", line)
+        }
+    }
+
+    pub fn archive_path(&self) -> PathBuf {
+        self.path.with_extension("archive.json")
+    }
+
+    /// Move the completed tasks matching `predicate` to the archive file, returning how many moved
+    ///
+    /// The tasks stay in the list when the archive cannot be read or written.
+    pub fn archive_tasks(&mut self, predicate: impl Fn(&Task) -> bool) -> usize {
+        let archived: Vec<Task> = self
+            .tasks
+            .iter()
+            .filter(|task| task.done && predicate(task))
+            .cloned()
+            .collect();
+        if archived.is_empty() {
+            return 0;
+        }
+        if let Err(e) = self.append_to_archive(&archived) {
+            eprintln!("{}", e);
+            return 0;
+        }
+        let ids: HashSet<i32> = archived.iter().map(|task| task.id).collect();
+        self.tasks.retain(|task| !ids.contains(&task.id));
+        self.save_tasks();
+        ids.len()
+    }
+
+    fn append_to_archive(&self, tasks: &[Task]) -> Result<(), ArchiveError> {
+        let path = self.archive_path();
+        let mut archive = BackendEnum::for_path(&path)
+            .open_store(&path)
+            .and_then(|store| TodoList::open(&path, store))
+            .map_err(|e| ArchiveError::Load(path.clone(), e))?;
+        archive.file_mode = self.file_mode;
+        archive.tasks.extend_from_slice(tasks);
+        archive.try_save_tasks().map_err(ArchiveError::Save)
+    }
+
+    /// Drop the completed tasks beyond `max_completed`, oldest completions first, returning how many
+    fn prune_completed(&mut self, now: DateTime<Local>) -> usize {
+        let Some(max) = self.max_completed else {
+            return 0;
+        };
+        let mut completions: Vec<_> = self
+            .tasks
+            .iter()
+            .filter(|task| task.done)
+            .map(|task| (task.completed_at, task.id))
+            .collect();
+        if completions.len() <= max {
+            return 0;
+        }
+        completions.sort_by(|a, b| b.cmp(a));
+        let kept: HashSet<i32> = completions.iter().take(max).map(|(_, id)| *id).collect();
+        let pruned: Vec<Task> = self
+            .tasks
+            .iter()
+            .filter(|task| task.done && !kept.contains(&task.id))
+            .cloned()
+            .collect();
+        // Kept for the next save when the archive cannot take them
+        if self.archive_pruned
+            && let Err(e) = self.append_to_archive(&pruned)
+        {
+            eprintln!("{}", e);
+            return 0;
+        }
+        self.tasks
+            .retain(|task| !task.done || kept.contains(&task.id));
+        for task in pruned.iter() {
+            self.log_event(now, &format!("prune {} {}", task.id, task.title));
+        }
+        pruned.len()
+    }
+
+    pub fn archive_completed_tasks(&mut self) {
+        let count = self.archive_tasks(|_| true);
+        println!("Archived {} tasks", count);
+    }
+
+    /// Move a task to the end of another list file under that list's next id, returning the new id
+    ///
+    /// The target is opened with the backend of its extension, and nothing moves when it cannot
+    /// be read. Tasks with subtasks stay, the moved task leaving its own parent behind.
+    pub fn transfer_task(&mut self, id: i32, to: &Path) -> Option<i32> {
+        if same_file(&self.path, to) {
+            eprintln!("The task is already in {}", to.display());
+            return None;
+        }
+        let Some(index) = self.tasks.iter().position(|task| task.id == id) else {
+            eprintln!("No task with id {}", id);
+            return None;
+        };
+        if self.tasks.iter().any(|task| task.parent_id == Some(id)) {
+            eprintln!("Task {} has subtasks, move or remove them first", id);
+            return None;
+        }
+        let target = BackendEnum::for_path(to)
+            .open_store(to)
+            .and_then(|store| TodoList::open(to, store));
+        let mut target = match target {
+            Ok(target) => target,
+            Err(e) => {
+                eprintln!("Could not read {} {}", to.display(), e);
+                return None;
+            }
+        };
+        // Written to the target first, so a failed write leaves the task where it was
+        let mut task = self.tasks[index].clone();
+        target.file_mode = self.file_mode;
+        task.id = target.next_id();
+        task.parent_id = None;
+        let new_id = task.id;
+        let event = format!("add {} {}", task.id, task.title);
+        target.tasks.push(task);
+        if let Err(e) = target.try_save_tasks() {
+            eprintln!("Could not write {} {}", to.display(), e);
+            return None;
+        }
+        let now = Local::now();
+        target.log_event(now, &event);
+        self.tasks.remove(index);
+        self.log_event(now, &format!("transfer {} {} {}", id, to.display(), new_id));
+        self.save_tasks();
+        Some(new_id)
+    }
+
+    /// Archive the tasks completed more than `days` days before `now`
+    pub fn auto_archive(&mut self, days: u32, now: DateTime<Local>) -> usize {
+        let Some(cutoff) = now.checked_sub_days(Days::new(days.into())) else {
+            return 0;
+        };
+        self.archive_tasks(|task| {
+            task.completed_at
+                .is_some_and(|completed| completed < cutoff)
+        })
+    }
+
+    /// Raise the priority of pending tasks sitting for more than `days` days, returning how many
+    pub fn auto_escalate(&mut self, days: u32, now: DateTime<Local>) -> usize {
+        let mut events = vec![];
+        for task in self.tasks.iter_mut() {
+            if task.escalate(days, now) {
+                events.push(format!(
+                    "escalate {} {:?}",
+                    task.id,
+                    task.priority.as_ref().unwrap()
+                ));
+            }
+        }
+        for event in events.iter() {
+            self.log_event(now, event);
+        }
+        if !events.is_empty() {
+            self.save_tasks();
+        }
+        events.len()
+    }
+
+    fn backup_path(&self, id: &str) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}.bak", id));
+        PathBuf::from(path)
+    }
+
+    pub fn backup_tasks(&self) {
+        self.backup_tasks_at(Local::now());
+    }
+
+    pub fn backup_tasks_at(&self, now: DateTime<Local>) {
+        let id = now.format("%Y%m%dT%H%M%S%3f").to_string();
+        match fs::copy(&self.path, self.backup_path(&id)) {
+            Ok(_) => println!("Created backup {}", id),
+            Err(e) => eprintln!("IO error {}", e),
+        }
+    }
+
+    /// Ids of the available backups, oldest first
+    pub fn backups(&self) -> Vec<String> {
+        let (Some(dir), Some(name)) = (self.path.parent(), self.path.file_name()) else {
+            return vec![];
+        };
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        let prefix = format!("{}.", name.to_string_lossy());
+        let mut backups: Vec<String> = fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| {
+                let file_name = entry.file_name().to_string_lossy().into_owned();
+                file_name
+                    .strip_prefix(&prefix)?
+                    .strip_suffix(".bak")
+                    .map(str::to_string)
+            })
+            .collect();
+        backups.sort();
+        backups
+    }
+
+    /// Restore the given backup, or the latest one, after confirmation
+    pub fn restore_backup(&mut self, which: Option<String>, input: &mut impl BufRead) {
+        let backups = self.backups();
+        if which.is_none() {
+            for backup in backups.iter() {
+                println!("{}", backup);
+            }
+        }
+        let Some(id) = which.or_else(|| backups.last().cloned()) else {
+            eprintln!("No backup available");
+            return;
+        };
+        if !backups.contains(&id) {
+            eprintln!("No backup {}", id);
+            return;
+        }
+        let backup = match fs::read_to_string(self.backup_path(&id))
+            .map_err(|e| e.to_string())
+            .and_then(|content| {
+                serde_json::from_str::<TodoList>(&content).map_err(|e| e.to_string())
+            }) {
+            Ok(backup) => backup,
+            Err(msg) => {
+                eprintln!("Backup {} is invalid {}", id, msg);
+                return;
+            }
+        };
+        if prompt::confirm(&format!("Restore backup {}?", id), input) {
+            // Everything the save file holds, the runtime settings staying as they are
+            let TodoList {
+                name,
+                description,
+                tasks,
+                ..
+            } = backup;
+            self.name = name;
+            self.description = description;
+            self.tasks = tasks;
+            self.save_tasks();
+        }
+    }
+
+    pub fn stats(&self) -> Stats {
+        Stats::from_tasks(&self.tasks)
+    }
+
+    /// Remove completed tasks, only those completed before `before` when given
+    pub fn clear_completed_tasks(&mut self, before: Option<DateTime<Local>>) -> usize {
+        let (cleared, kept): (Vec<Task>, Vec<Task>) = std::mem::take(&mut self.tasks)
+            .into_iter()
+            .partition(|task| {
+                task.done
+                    && before.is_none_or(|before| {
+                        task.completed_at
+                            .is_some_and(|completed| completed < before)
+                    })
+            });
+        self.tasks = kept;
+        let now = Local::now();
+        for task in cleared.iter() {
+            self.log_event(now, &format!("remove {} {}", task.id, task.title));
+        }
+        self.save_tasks();
+        cleared.len()
+    }
+
+    /// Reassign ids from 1 in the current order, remapping subtask parents
+    pub fn renumber_ids(&mut self) {
+        let ids: HashMap<i32, i32> = self
+            .tasks
+            .iter()
+            .zip(1..)
+            .map(|(task, id)| (task.id, id))
+            .collect();
+        for task in self.tasks.iter_mut() {
+            task.id = ids[&task.id];
+            task.parent_id = task.parent_id.and_then(|parent| ids.get(&parent).copied());
+        }
+        self.save_tasks();
+    }
+
+    pub fn reset_tasks(&mut self) {
+        for task in self.tasks.iter_mut() {
+            task.set_status(StatusEnum::Todo, Local::now());
+        }
+        self.save_tasks();
+    }
+
+    /// The saved form of the list as a YAML value, without the runtime settings
+    pub fn to_yaml_value(&self) -> Result<serde_yml::Value, serde_yml::Error> {
+        serde_yml::to_value(self)
+    }
+
+    /// Export to `out`, or next to the save file when not given
+    pub fn export_tasks(&self, format: FormatEnum, options: &ExportOptions) {
+        if let Err(e) = self.export(format, options) {
+            eprintln!("{}", e);
+        }
+    }
+
+    /// Export the tasks, refusing to write over the save file unless forced
+    pub fn export(&self, format: FormatEnum, options: &ExportOptions) -> Result<(), ExportError> {
+        let path = match &options.out {
+            Some(out) => {
+                if let Some(parent) = out.parent() {
+                    fs::create_dir_all(parent).map_err(ExportError::IoError)?;
+                }
+                out.to_path_buf()
+            }
+            None => format.default_path(&self.path),
+        };
+        if !options.force && same_file(&path, &self.path) {
+            return Err(ExportError::WouldOverwriteSave(path));
+        }
+        let exporter: Box<dyn Exporter> = match (&format, &options.indent) {
+            (FormatEnum::Json, Some(indent)) => Box::new(JsonExporter {
+                indent: indent.clone(),
+            }),
+            _ => format.exporter(),
+        };
+
+        let todolist = &self.sorted_copy(options.sort);
+        if options.append {
+            exporter.append(todolist, &path)
+        } else {
+            exporter.export_to_path(todolist, &path)
+        }
+    }
+
+    /// Append the tasks of another file, renumbering them after the current last id
+    pub fn import_tasks(&mut self, file: &Path, format: FormatEnum) {
+        match importer::import(file, format) {
+            Ok(mut tasks) => {
+                let count = tasks.len();
+                let mut new_ids = HashMap::new();
+                for (id, task) in (self.next_id()..).zip(tasks.iter_mut()) {
+                    new_ids.insert(task.id, id);
+                    task.id = id;
+                }
+                // Subtasks follow their parent to its new id, or lose it when it was not imported
+                for mut task in tasks {
+                    task.parent_id = task.parent_id.and_then(|id| new_ids.get(&id).copied());
+                    task.sync_status();
+                    self.tasks.push(task);
+                }
+                self.save_tasks();
+                println!("Imported {} tasks", count);
+            }
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    /// Compare with other tasks by id: `-` only here, `+` only there, `~` changed
+    pub fn diff(&self, other: &[Task]) -> Vec<String> {
+        let mut lines = vec![];
+        for task in self.tasks.iter() {
+            match other.iter().find(|o| o.id == task.id) {
+                None => lines.push(format!("- {} {}", task.id, task.title)),
+                Some(o) => {
+                    let mut changes = vec![];
+                    if o.title != task.title {
+                        changes.push(format!("title \"{}\" → \"{}\"", task.title, o.title));
+                    }
+                    if o.done != task.done {
+                        changes.push(format!("done {} → {}", task.done, o.done));
+                    }
+                    if !changes.is_empty() {
+                        lines.push(format!(
+                            "~ {} {}: {}",
+                            task.id,
+                            task.title,
+                            changes.join(", ")
+                        ));
+                    }
+                }
+            }
+        }
+        for o in other.iter() {
+            if !self.tasks.iter().any(|task| task.id == o.id) {
+                lines.push(format!("+ {} {}", o.id, o.title));
+            }
+        }
+        lines
+    }
+
+    pub fn diff_tasks(&self, other: &Path) {
+        match importer::import(other, FormatEnum::Json) {
+            Ok(tasks) => {
+                for line in self.diff(&tasks) {
+                    println!("{}", line);
+                }
+            }
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    pub fn save_tasks(&mut self) {
+        if let Err(e) = self.try_save_tasks() {
+            eprintln!("{}", e);
+        }
+    }
+
+    /// Like `save_tasks`, but return why nothing was written instead of printing it
+    pub fn try_save_tasks(&mut self) -> Result<(), SaveError> {
+        if self.read_only {
+            return Err(SaveError::ReadOnly(self.path.clone()));
+        }
+        if self.interrupt.interrupted() {
+            return Err(SaveError::Interrupted);
+        }
+        self.prune_completed(Local::now());
+        self.interrupt.begin_save();
+        let saved = self.store.save(self).map_err(SaveError::Export);
+        self.interrupt.end_save();
+        saved
+    }
+
+    /// Load the list saved in `store`, `path` being where archives, backups and exports go next to
+    pub fn open(path: &Path, store: Box<dyn Store>) -> Result<Self, LoadError> {
+        let mut todolist = store.load()?.unwrap_or_else(|| TodoList::empty(path));
+        todolist.path = path.to_path_buf();
+        todolist.store = store;
+        for task in todolist.tasks.iter_mut() {
+            task.sync_status();
+            task.modified_at.get_or_insert(task.created_at);
+        }
+        let backfilled = todolist.backfill_completed_at();
+        if backfilled > 0 {
+            eprintln!("Backfilled completion date of {} tasks", backfilled);
+        }
+        Ok(todolist)
+    }
+
+    pub fn load_tasks(path: PathBuf) -> Self {
+        match TodoList::open(&path, Box::new(FileStore::new(path.clone()))) {
+            Ok(todolist) => todolist,
+            // Unparsable or unreadable, the file is kept as it is rather than saved over empty
+            Err(e) => {
+                eprintln!("Could not read {} {}", path.display(), e);
+                let mut todolist = TodoList::empty(&path);
+                todolist.read_only = true;
+                todolist
+            }
+        }
+    }
+
+    /// Like `load_tasks`, but refuse files with fields unknown to the schema
+    pub fn load_tasks_strict(path: PathBuf) -> Result<Self, LoadError> {
+        let store = FileStore {
+            path: path.clone(),
+            strict: true,
+        };
+        TodoList::open(&path, Box::new(store))
+    }
+
+    /// Parse a save file strictly and check it is consistent, returning the problems found
+    ///
+    /// Nothing is written, not even the fixes `open` makes to older files.
+    pub fn validate(path: &Path) -> Result<Vec<String>, LoadError> {
+        let store = FileStore {
+            path: path.to_path_buf(),
+            strict: true,
+        };
+        match store.load()? {
+            Some(todolist) => Ok(todolist.problems()),
+            None => Err(LoadError::IoError(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} does not exist", path.display()),
+            ))),
+        }
+    }
+
+    /// What makes the list inconsistent: duplicate ids, subtasks of missing tasks, parent cycles,
+    /// completion dates on pending tasks and progress past 100%
+    ///
+    /// What `open` fixes in older files, like a missing status, is not a problem.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        let mut ids = HashSet::new();
+        for task in self.tasks.iter() {
+            if task.id < 1 {
+                problems.push(format!("Task {} has an id below 1", task.id));
+            }
+            if !ids.insert(task.id) {
+                problems.push(format!("Task id {} is used more than once", task.id));
+            }
+        }
+        for task in self.tasks.iter() {
+            if let Some(parent_id) = task.parent_id {
+                if parent_id == task.id {
+                    problems.push(format!("Task {} is its own parent", task.id));
+                } else if !ids.contains(&parent_id) {
+                    problems.push(format!(
+                        "Task {} is a subtask of task {}, which does not exist",
+                        task.id, parent_id
+                    ));
+                } else if self.in_parent_cycle(task) {
+                    problems.push(format!("Task {} is in a cycle of parents", task.id));
+                }
+            }
+            if !task.done && task.completed_at.is_some() {
+                problems.push(format!(
+                    "Task {} is pending but has a completion date",
+                    task.id
+                ));
+            }
+            if task.progress > 100 {
+                problems.push(format!(
+                    "Task {} has a progress of {}%",
+                    task.id, task.progress
+                ));
+            }
+        }
+        problems
+    }
+
+    /// Whether following the parents of `task` leads back to it
+    fn in_parent_cycle(&self, task: &Task) -> bool {
+        let mut seen = HashSet::from([task.id]);
+        let mut parent_id = task.parent_id;
+        while let Some(id) = parent_id {
+            if id == task.id {
+                return true;
+            }
+            if !seen.insert(id) {
+                return false;
+            }
+            parent_id = self
+                .tasks
+                .iter()
+                .find(|parent| parent.id == id)
+                .and_then(|parent| parent.parent_id);
+        }
+        false
+    }
+
+    /// Older files may have done tasks without a completion date, use their creation date instead
+    fn backfill_completed_at(&mut self) -> usize {
+        let mut backfilled = 0;
+        for task in self.tasks.iter_mut() {
+            if task.done && task.completed_at.is_none() {
+                task.completed_at = Some(task.created_at);
+                backfilled += 1;
+            }
+        }
+        backfilled
+    }
+
+    pub fn completed_tasks<'a>(&'a self) -> CompletedTasksIter<'a> {
+        CompletedTasksIter {
+            inner: self.tasks.iter(),
+        }
+    }
+
+    pub fn pending_tasks<'a>(&'a self) -> PendingTasksIter<'a> {
+        PendingTasksIter {
+            inner: self.tasks.iter(),
+        }
+    }
+
+    /// The tasks matching `filter`, in list order, checked as the iterator advances
+    ///
+    /// ```
+    /// use todo_cli::{filter::TaskFilter, store::MemoryStore, todolist::TodoList};
+    ///
+    /// let mut todolist = TodoList::open("todo.json".as_ref(), Box::<MemoryStore>::default()).unwrap();
+    /// todolist.add_task("Buy milk".to_string(), None);
+    /// let filter = TaskFilter::new().pending();
+    /// assert_eq!(todolist.iter_filtered(&filter).count(), 1);
+    /// ```
+    pub fn iter_filtered<'a>(&'a self, filter: &'a TaskFilter) -> impl Iterator<Item = &'a Task> {
+        self.tasks.iter().filter(move |task| filter.matches(task))
+    }
+}
+
+impl<'a> IntoIterator for &'a TodoList {
+    type Item = &'a Task;
+    type IntoIter = std::slice::Iter<'a, Task>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.tasks.iter()
+    }
+}
+
+/// The saved form of the list, without the runtime settings
+impl TryFrom<&TodoList> for serde_json::Value {
+    type Error = serde_json::Error;
+    fn try_from(todolist: &TodoList) -> Result<Self, Self::Error> {
+        serde_json::to_value(todolist)
+    }
+}
+
+pub struct CompletedTasksIter<'a> {
+    inner: std::slice::Iter<'a, Task>,
+}
+
+impl<'a> Iterator for CompletedTasksIter<'a> {
+    type Item = &'a Task;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.by_ref().find(|&task| task.done).map(|v| v as _)
+    }
+}
+
+pub struct PendingTasksIter<'a> {
+    inner: std::slice::Iter<'a, Task>,
+}
+
+impl<'a> Iterator for PendingTasksIter<'a> {
+    type Item = &'a Task;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.by_ref().find(|&task| !task.done).map(|v| v as _)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::effective_width;
+    use crate::color::{ColorByEnum, ColorEnum, GREEN, MAGENTA, RED, YELLOW, tag_color};
+    use crate::task::RecurrenceEnum;
+    use chrono::TimeZone;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_new_todolist_is_empty() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let todolist = TodoList::new(&path);
+        assert_eq!(todolist.tasks.len(), 0);
+        assert_eq!(&todolist.path, &path);
+    }
+
+    #[test]
+    fn test_add_task_increase_length() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        assert_eq!(todolist.tasks.len(), 1);
+        todolist.add_task("task 2".to_string(), None);
+        assert_eq!(todolist.tasks.len(), 2);
+    }
+
+    #[test]
+    fn test_remove_task_decrease_length() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        todolist.add_task("task 2".to_string(), None);
+        todolist.remove_task(2);
+        assert_eq!(todolist.tasks.len(), 1);
+    }
+
+    #[test]
+    fn test_add_tasks_with_priority() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), Some(PriorityEnum::High));
+        todolist.add_task("task 2".to_string(), Some(PriorityEnum::Medium));
+        todolist.add_task("task 2".to_string(), Some(PriorityEnum::Low));
+        todolist.add_task("task 2".to_string(), None);
+        assert_eq!(
+            todolist.tasks.first().unwrap().priority,
+            Some(PriorityEnum::High)
+        );
+        assert_eq!(
+            todolist.tasks.get(1).unwrap().priority,
+            Some(PriorityEnum::Medium)
+        );
+        assert_eq!(
+            todolist.tasks.get(2).unwrap().priority,
+            Some(PriorityEnum::Low)
+        );
+        assert_eq!(todolist.tasks.get(3).unwrap().priority, None);
+    }
+
+    #[test]
+    fn test_task_ids_are_incremented() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        assert_eq!(todolist.tasks.first().unwrap().id, 1);
+        todolist.add_task("task 2".to_string(), None);
+        assert_eq!(todolist.tasks.get(1).unwrap().id, 2);
+    }
+
+    #[test]
+    fn test_complete_task_changes_status() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        assert!(!todolist.tasks.first().unwrap().done);
+        todolist.complete_task(1);
+        assert!(todolist.tasks.first().unwrap().done);
+    }
+
+    #[test]
+    fn test_complete_task_changes_completed_at() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        assert_eq!(todolist.tasks.first().unwrap().completed_at, None);
+        todolist.complete_task(1);
+        assert_ne!(todolist.tasks.first().unwrap().completed_at, None);
+    }
+
+    #[test]
+    fn test_completed_task_iterator() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        todolist.add_task("task 2".to_string(), None);
+        todolist.add_task("task 3".to_string(), None);
+        let completed_tasks: Vec<&Task> = todolist.completed_tasks().collect();
+        assert_eq!(completed_tasks.len(), 0);
+        todolist.complete_task(2);
+        todolist.complete_task(3);
+        let completed_tasks: Vec<&Task> = todolist.completed_tasks().collect();
+        assert_eq!(completed_tasks.len(), 2);
+    }
+
+    #[test]
+    fn test_pending_task_iterator() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        todolist.add_task("task 2".to_string(), None);
+        todolist.add_task("task 3".to_string(), None);
+        let pending_tasks: Vec<&Task> = todolist.pending_tasks().collect();
+        assert_eq!(pending_tasks.len(), 3);
+        todolist.complete_task(2);
+        todolist.complete_task(3);
+        let pending_tasks: Vec<&Task> = todolist.pending_tasks().collect();
+        assert_eq!(pending_tasks.len(), 1);
+    }
+
+    #[test]
+    fn test_reset_all_tasks() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        todolist.add_task("task 2".to_string(), None);
+        todolist.add_task("task 3".to_string(), None);
+        todolist.complete_task(1);
+        todolist.complete_task(2);
+        todolist.reset_tasks();
+        let pending_tasks: Vec<&Task> = todolist.pending_tasks().collect();
+        assert_eq!(pending_tasks.len(), 3);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        todolist.add_task("task 2".to_string(), Some(PriorityEnum::High));
+        todolist.add_task("task 3".to_string(), None);
+        todolist.complete_task(2);
+        todolist.save_tasks();
+
+        let content = fs::read_to_string(&path).unwrap();
+        let loaded: TodoList = serde_json::from_str(&content).unwrap();
+
+        assert_eq!(loaded.tasks.len(), 3);
+        assert_eq!(loaded.tasks[0].title, "task 1");
+        assert!(!loaded.tasks[0].done);
+        assert_eq!(loaded.tasks[1].title, "task 2");
+        assert!(loaded.tasks[1].done);
+        assert_eq!(loaded.tasks[1].priority, Some(PriorityEnum::High));
+        assert_ne!(loaded.tasks[1].completed_at, None);
+        assert_eq!(loaded.tasks[2].title, "task 3");
+        assert!(!loaded.tasks[2].done);
+    }
+
+    #[test]
+    fn test_color_modes_on_non_tty() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), Some(PriorityEnum::High));
+        let task = todolist.tasks.first().unwrap();
+
+        todolist.color = ColorEnum::Auto.enabled(false);
+        assert!(
+            !task
+                .render(
+                    todolist.color,
+                    &todolist.theme,
+                    todolist.timestamps,
+                    1,
+                    todolist.width
+                )
+                .contains("\x1b[")
+        );
+        todolist.color = ColorEnum::Always.enabled(false);
+        assert!(
+            task.render(
+                todolist.color,
+                &todolist.theme,
+                todolist.timestamps,
+                1,
+                todolist.width
+            )
+            .contains("\x1b[")
+        );
+        todolist.color = ColorEnum::Never.enabled(false);
+        assert!(
+            !task
+                .render(
+                    todolist.color,
+                    &todolist.theme,
+                    todolist.timestamps,
+                    1,
+                    todolist.width
+                )
+                .contains("\x1b[")
+        );
+    }
+
+    #[test]
+    fn test_list_timestamps() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        let mut task = Task::new(1, "report".to_string(), None);
+        task.created_at = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        task.due_at = Some(Local.with_ymd_and_hms(2024, 6, 7, 17, 0, 0).unwrap());
+        todolist.insert_task(task);
+
+        todolist.timestamps = TimestampEnum::Date;
+        assert_eq!(
+            todolist.render_tasks(todolist.tasks.iter()),
+            vec!["1 ❌ report - Created on 2024-06-01 - Due on 2024-06-07"]
+        );
+        todolist.timestamps = TimestampEnum::None;
+        todolist.complete_task_at(1, Local.with_ymd_and_hms(2024, 6, 3, 12, 0, 0).unwrap());
+        assert_eq!(
+            todolist.render_tasks(todolist.tasks.iter()),
+            vec!["1 ✅ report - Due on 2024-06-07"]
+        );
+    }
+
+    #[test]
+    fn test_color_by_due() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        let now = Local::now();
+        for (title, due) in [
+            ("overdue", Some(now - chrono::Duration::days(1))),
+            ("soon", Some(now + chrono::Duration::days(1))),
+            ("far", Some(now + chrono::Duration::days(30))),
+            ("no due", None),
+        ] {
+            let mut task = Task::new(todolist.next_id(), title.to_string(), None);
+            task.due_at = due;
+            todolist.insert_task(task);
+        }
+        todolist.color = true;
+        todolist.color_by = Some(ColorByEnum::Due);
+
+        let lines = todolist.render_tasks(todolist.tasks.iter());
+        assert!(lines[0].starts_with(RED));
+        assert!(lines[1].starts_with(YELLOW));
+        assert!(lines[2].starts_with(GREEN));
+        assert!(!lines[3].contains("\x1b["));
+
+        todolist.color = false;
+        let lines = todolist.render_tasks(todolist.tasks.iter());
+        assert!(lines.iter().all(|line| !line.contains("\x1b[")));
+    }
+
+    #[test]
+    fn test_color_by_priority_status_and_tag() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("high".to_string(), Some(PriorityEnum::High));
+        todolist.add_task("medium".to_string(), Some(PriorityEnum::Medium));
+        todolist.add_task("none".to_string(), None);
+        todolist.set_status(2, StatusEnum::Waiting);
+        todolist.tag_task(1, &["work".to_string()], &[]);
+        todolist.tag_task(3, &["home".to_string()], &[]);
+        todolist.color = true;
+
+        todolist.color_by = Some(ColorByEnum::Priority);
+        let lines = todolist.render_tasks(todolist.tasks.iter());
+        assert!(lines[0].starts_with(RED));
+        assert!(lines[1].starts_with(YELLOW));
+        assert!(!lines[2].contains("\x1b["));
+
+        todolist.color_by = Some(ColorByEnum::Status);
+        let lines = todolist.render_tasks(todolist.tasks.iter());
+        assert!(lines[1].starts_with(MAGENTA));
+
+        todolist.color_by = Some(ColorByEnum::Tag);
+        let lines = todolist.render_tasks(todolist.tasks.iter());
+        assert!(lines[0].starts_with(tag_color("work")));
+        assert!(!lines[1].contains("\x1b["));
+        assert!(lines[2].starts_with(tag_color("home")));
+        assert_ne!(tag_color("work"), tag_color("home"));
+    }
+
+    #[test]
+    fn test_forecast_daily_task() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        let mut task = Task::new(todolist.next_id(), "task 1".to_string(), None);
+        task.due_at = Some(Local.with_ymd_and_hms(2024, 6, 1, 9, 0, 0).unwrap());
+        task.recurrence = Some(RecurrenceEnum::Daily);
+        todolist.insert_task(task);
+        let forecast = todolist.tasks[0].forecast(3);
+        assert_eq!(
+            forecast,
+            vec![
+                Local.with_ymd_and_hms(2024, 6, 1, 9, 0, 0).unwrap(),
+                Local.with_ymd_and_hms(2024, 6, 2, 9, 0, 0).unwrap(),
+                Local.with_ymd_and_hms(2024, 6, 3, 9, 0, 0).unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_forecast_weekly_task() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        let mut task = Task::new(todolist.next_id(), "task 1".to_string(), None);
+        task.due_at = Some(Local.with_ymd_and_hms(2024, 6, 28, 9, 0, 0).unwrap());
+        task.recurrence = Some(RecurrenceEnum::Weekly);
+        todolist.insert_task(task);
+        let forecast = todolist.tasks[0].forecast(3);
+        assert_eq!(
+            forecast,
+            vec![
+                Local.with_ymd_and_hms(2024, 6, 28, 9, 0, 0).unwrap(),
+                Local.with_ymd_and_hms(2024, 7, 5, 9, 0, 0).unwrap(),
+                Local.with_ymd_and_hms(2024, 7, 12, 9, 0, 0).unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_forecast_non_recurring_task_is_empty() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        assert!(todolist.tasks[0].forecast(3).is_empty());
+    }
+
+    #[test]
+    fn test_render_tasks_pads_ids() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        todolist.insert_task(Task::new(100, "task 100".to_string(), None));
+        let lines = todolist.render_tasks(todolist.tasks.iter());
+        assert!(lines[0].starts_with("  1 ❌ task 1"));
+        assert!(lines[1].starts_with("100 ❌ task 100"));
+        assert_eq!(lines[0].find('❌'), lines[1].find('❌'));
+    }
+
+    #[test]
+    fn test_render_wraps_description() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        let mut task = Task::new(todolist.next_id(), "task 1".to_string(), None);
+        task.description = Some("one two three four five six\nseven".to_string());
+        todolist.insert_task(task);
+        todolist.width = Some(20);
+        let lines = todolist.render_tasks(todolist.tasks.iter());
+        let rendered: Vec<&str> = lines[0].lines().skip(1).collect();
+        assert_eq!(
+            rendered,
+            vec!["     one two three", "     four five six", "     seven"]
+        );
+    }
+
+    #[test]
+    fn test_render_without_width_keeps_description_whole() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        let description = "word ".repeat(40).trim_end().to_string();
+        let mut task = Task::new(todolist.next_id(), "task 1".to_string(), None);
+        task.description = Some(format!("{}\nsecond line", description));
+        todolist.insert_task(task);
+        todolist.width = effective_width(false, Some(20));
+        let lines = todolist.render_tasks(todolist.tasks.iter());
+        let rendered: Vec<&str> = lines[0].lines().skip(1).collect();
+        assert_eq!(
+            rendered,
+            vec![format!("     {}", description).as_str(), "     second line"]
+        );
+    }
+
+    #[test]
+    fn test_unreadable_save_file_is_never_overwritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        fs::create_dir(&path).unwrap();
+
+        let mut todolist = TodoList::load_tasks(path.clone());
+        assert!(todolist.read_only);
+        assert!(todolist.tasks.is_empty());
+        todolist.add_task("task 1".to_string(), None);
+        assert!(path.is_dir());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        // A missing file is only a list not saved yet
+        let todolist = TodoList::load_tasks(dir.path().join("new.json"));
+        assert!(!todolist.read_only);
+    }
+
+    #[test]
+    fn test_load_backfills_legacy_completed_at() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        fs::write(
+            &path,
+            r#"{"tasks":[
+                {"id":1,"title":"task 1","done":true,"created_at":"2024-06-01T09:00:00+00:00","completed_at":null,"priority":null},
+                {"id":2,"title":"task 2","done":false,"created_at":"2024-06-01T09:00:00+00:00","completed_at":null,"priority":null}
+            ]}"#,
+        )
+        .unwrap();
+        let todolist = TodoList::load_tasks(path);
+        assert_eq!(
+            todolist.tasks[0].completed_at,
+            Some(todolist.tasks[0].created_at)
+        );
+        assert_eq!(todolist.tasks[1].completed_at, None);
+    }
+
+    #[test]
+    fn test_burndown_counts_open_tasks_per_day() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        let day = |d| Local.with_ymd_and_hms(2024, 6, d, 12, 0, 0).unwrap();
+        for (created, completed) in [(1, Some(3)), (2, None), (2, Some(2)), (4, None)] {
+            let mut task = Task::new(todolist.next_id(), "task".to_string(), None);
+            task.created_at = day(created);
+            task.done = completed.is_some();
+            task.completed_at = completed.map(day);
+            todolist.insert_task(task);
+        }
+        let series: Vec<usize> = todolist
+            .burndown(5, NaiveDate::from_ymd_opt(2024, 6, 5).unwrap())
+            .into_iter()
+            .map(|(_, open)| open)
+            .collect();
+        assert_eq!(series, vec![1, 2, 1, 2, 2]);
+    }
+
+    #[test]
+    fn test_completions_by_day_uses_local_dates() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        let at = |d, h, m| Local.with_ymd_and_hms(2024, 6, d, h, m, 0).unwrap();
+        for completed in [
+            Some(at(1, 12, 0)),
+            Some(at(3, 0, 0)),
+            Some(at(3, 23, 59)),
+            Some(at(4, 0, 1)),
+            Some(at(5, 8, 0)),
+            None,
+        ] {
+            let mut task = Task::new(todolist.next_id(), "task".to_string(), None);
+            task.created_at = at(1, 0, 0);
+            if let Some(completed) = completed {
+                task.complete(completed);
+            }
+            todolist.insert_task(task);
+        }
+        let today = NaiveDate::from_ymd_opt(2024, 6, 5).unwrap();
+        let counts: Vec<usize> = todolist
+            .completions_by_day(4, today)
+            .into_iter()
+            .map(|(_, done)| done)
+            .collect();
+        // The completion on June 1st falls outside the window
+        assert_eq!(counts, vec![0, 2, 1, 1]);
+        assert_eq!(todolist.completions_by_day(1, today), vec![(today, 1)]);
+    }
+
+    #[test]
+    fn test_csv_export_escapes_special_characters() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("a,\"b\"\nc".to_string(), Some(PriorityEnum::Low));
+        todolist.export_tasks(FormatEnum::Csv, &ExportOptions::default());
+
+        let mut reader = csv::Reader::from_path(path.with_extension("csv")).unwrap();
+        let imported: Vec<Task> = reader
+            .deserialize()
+            .map(|row: Result<CsvTask, _>| Task::from(row.unwrap()))
+            .collect();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].title, "a,\"b\"\nc");
+        assert_eq!(imported[0].priority, Some(PriorityEnum::Low));
+    }
+
+    #[test]
+    fn test_import_csv_renumbers_ids() {
+        let source_path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut source = TodoList::new(&source_path);
+        source.add_task("task 1".to_string(), None);
+        source.add_task("task 2".to_string(), Some(PriorityEnum::High));
+        source.export_tasks(FormatEnum::Csv, &ExportOptions::default());
+
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("existing".to_string(), None);
+        todolist.import_tasks(&source_path.with_extension("csv"), FormatEnum::Csv);
+        assert_eq!(todolist.tasks.len(), 3);
+        assert_eq!(todolist.tasks[1].id, 2);
+        assert_eq!(todolist.tasks[2].id, 3);
+        assert_eq!(todolist.tasks[2].title, "task 2");
+        assert_eq!(todolist.tasks[2].priority, Some(PriorityEnum::High));
+    }
+
+    #[test]
+    fn test_import_remaps_parent_ids() {
+        let source_path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut source = TodoList::new(&source_path);
+        for (title, parent) in [("release", None), ("docs", Some(1)), ("orphan", Some(7))] {
+            let mut task = Task::new(source.next_id(), title.to_string(), None);
+            task.parent_id = parent;
+            source.insert_task(task);
+        }
+        source.export_tasks(FormatEnum::Jsonl, &ExportOptions::default());
+
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("existing".to_string(), None);
+        todolist.add_task("existing".to_string(), None);
+        todolist.import_tasks(&source_path.with_extension("jsonl"), FormatEnum::Jsonl);
+        let parents: Vec<(i32, Option<i32>)> = todolist
+            .tasks
+            .iter()
+            .map(|task| (task.id, task.parent_id))
+            .collect();
+        assert_eq!(
+            parents,
+            vec![(1, None), (2, None), (3, None), (4, Some(3)), (5, None)]
+        );
+    }
+
+    #[test]
+    fn test_import_yaml_renumbers_ids() {
+        let source_path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut source = TodoList::new(&source_path);
+        source.add_task("task 1".to_string(), None);
+        source.complete_task(1);
+        source.export_tasks(FormatEnum::Yaml, &ExportOptions::default());
+
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("existing".to_string(), None);
+        todolist.import_tasks(&source_path.with_extension("yaml"), FormatEnum::Yaml);
+        assert_eq!(todolist.tasks.len(), 2);
+        assert_eq!(todolist.tasks[1].id, 2);
+        assert_eq!(todolist.tasks[1].title, "task 1");
+        assert!(todolist.tasks[1].done);
+    }
+
+    #[test]
+    fn test_strict_load_rejects_unknown_fields() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        fs::write(
+            &path,
+            r#"{"tasks":[{"id":1,"title":"task 1","done":false,"created_at":"2024-06-01T09:00:00+00:00","completed_at":null,"priority":null,"colour":"red"}]}"#,
+        )
+        .unwrap();
+        match TodoList::load_tasks_strict(path.clone()) {
+            Err(LoadError::UnknownFields(fields)) => assert_eq!(fields, vec!["tasks.0.colour"]),
+            other => panic!("expected unknown fields, got {:?}", other),
+        }
+
+        let todolist = TodoList::load_tasks(path);
+        assert_eq!(todolist.tasks.len(), 1);
+        assert_eq!(todolist.tasks[0].title, "task 1");
+    }
+
+    #[test]
+    fn test_complete_task_stops_running_timer() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        todolist.start_task(1);
+        let started_at = todolist.tasks[0].started_at.unwrap();
+        todolist.complete_task_at(1, started_at + chrono::Duration::seconds(90));
+        assert_eq!(todolist.tasks[0].started_at, None);
+        assert_eq!(todolist.tasks[0].total_seconds, 90);
+        assert!(todolist.tasks[0].done);
+    }
+
+    #[test]
+    fn test_update_unknown_task_is_not_saved() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+
+        todolist.update_task(1, |task| task.priority = Some(PriorityEnum::High));
+        assert_eq!(
+            TodoList::load_tasks(path.clone()).tasks[0].priority,
+            Some(PriorityEnum::High)
+        );
+
+        fs::remove_file(&path).unwrap();
+        todolist.update_task(2, |task| task.priority = Some(PriorityEnum::Low));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_timer_on_unknown_task_is_not_saved() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+
+        fs::remove_file(&path).unwrap();
+        todolist.start_task(2);
+        todolist.stop_task(2);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_render_selected_fields() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        let mut task = Task::new(todolist.next_id(), "task 1".to_string(), None);
+        task.due_at = Some(Local.with_ymd_and_hms(2024, 6, 1, 9, 0, 0).unwrap());
+        todolist.insert_task(task);
+
+        todolist.fields = vec![FieldEnum::Id, FieldEnum::Title];
+        assert_eq!(
+            todolist.render_tasks(todolist.tasks.iter()),
+            vec!["1 - task 1"]
+        );
+
+        todolist.fields = vec![FieldEnum::Title, FieldEnum::Id, FieldEnum::Due];
+        assert_eq!(
+            todolist.render_tasks(todolist.tasks.iter()),
+            vec![format!(
+                "task 1 - 1 - {}",
+                todolist.tasks[0].due_at.unwrap()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_interrupt_skips_save() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        assert!(todolist.interrupt.handle());
+        assert!(todolist.interrupt.interrupted());
+        todolist.add_task("task 2".to_string(), None);
+
+        let loaded = TodoList::load_tasks(path);
+        assert_eq!(loaded.tasks.len(), 1);
+    }
+
+    #[test]
+    fn test_interrupt_waits_for_save_in_progress() {
+        let interrupt = Interrupt::default();
+        interrupt.begin_save();
+        assert!(!interrupt.handle());
+        assert!(interrupt.interrupted());
+    }
+
+    #[test]
+    fn test_save_leaves_no_temporary_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        let mut files: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        files.sort();
+        assert_eq!(files, vec!["todo.json", "todo.json.log"]);
+    }
+
+    #[test]
+    fn test_diff_lists_each_kind_of_difference() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("same".to_string(), None);
+        todolist.add_task("renamed".to_string(), None);
+        todolist.add_task("completed".to_string(), None);
+        todolist.add_task("only here".to_string(), None);
+
+        let other_path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut other = TodoList::new(&other_path);
+        other.add_task("same".to_string(), None);
+        other.add_task("renamed again".to_string(), None);
+        other.add_task("completed".to_string(), None);
+        other.complete_task(3);
+        other.insert_task(Task::new(5, "only there".to_string(), None));
+
+        assert_eq!(
+            todolist.diff(&other.tasks),
+            vec![
+                "~ 2 renamed: title \"renamed\" → \"renamed again\"",
+                "~ 3 completed: done false → true",
+                "- 4 only here",
+                "+ 5 only there",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_markdown_export_groups_by_status() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        todolist.add_task("task 2".to_string(), None);
+        todolist.complete_task(2);
+        todolist.export_tasks(FormatEnum::Markdown, &ExportOptions::default());
+
+        let markdown = fs::read_to_string(path.with_extension("md")).unwrap();
+        let pending = markdown.find("## Pending").unwrap();
+        let completed = markdown.find("## Completed").unwrap();
+        let task_1 = markdown.find("- [ ] task 1").unwrap();
+        let task_2 = markdown.find("- [x] task 2").unwrap();
+        assert!(pending < task_1 && task_1 < completed && completed < task_2);
+    }
+
+    #[test]
+    fn test_status_line_counts() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        let now = Local.with_ymd_and_hms(2024, 6, 10, 12, 0, 0).unwrap();
+        todolist.add_task("task 1".to_string(), None);
+        todolist.add_task("task 2".to_string(), None);
+        let mut task = Task::new(todolist.next_id(), "task 3".to_string(), None);
+        task.due_at = Some(Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap());
+        todolist.insert_task(task);
+        todolist.complete_task(1);
+        assert_eq!(todolist.status_line(now), "✓1 ◷2 ⚠1");
+    }
+
+    #[test]
+    fn test_status_prompt_has_no_trailing_newline() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+
+        let mut out = vec![];
+        todolist.write_status(&mut out, true).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "✓0 ◷1 ⚠0");
+
+        let mut out = vec![];
+        todolist.write_status(&mut out, false).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "✓0 ◷1 ⚠0\n");
+    }
+
+    #[test]
+    fn test_auto_archive_moves_old_completed_tasks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let mut todolist = TodoList::new(&path);
+        let now = Local.with_ymd_and_hms(2024, 6, 10, 12, 0, 0).unwrap();
+        todolist.add_task("old".to_string(), None);
+        todolist.add_task("recent".to_string(), None);
+        todolist.add_task("pending".to_string(), None);
+        todolist.complete_task_at(1, now - chrono::Duration::days(10));
+        todolist.complete_task_at(2, now - chrono::Duration::days(2));
+
+        assert_eq!(todolist.auto_archive(7, now), 1);
+        let titles: Vec<&str> = todolist.tasks.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, vec!["recent", "pending"]);
+
+        let archive = TodoList::load_tasks(todolist.archive_path());
+        assert_eq!(archive.tasks.len(), 1);
+        assert_eq!(archive.tasks[0].title, "old");
+        assert_eq!(TodoList::load_tasks(path).tasks.len(), 2);
+    }
+
+    #[test]
+    fn test_max_completed_keeps_the_newest_completions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let mut todolist = TodoList::new(&path);
+        todolist.max_completed = Some(2);
+        let now = Local.with_ymd_and_hms(2024, 6, 10, 12, 0, 0).unwrap();
+        for i in 1..=5 {
+            todolist.add_task(format!("task {}", i), None);
+        }
+        todolist.complete_task_at(4, now - chrono::Duration::days(4));
+        todolist.complete_task_at(1, now - chrono::Duration::days(3));
+        todolist.complete_task_at(2, now - chrono::Duration::days(1));
+        todolist.complete_task_at(3, now - chrono::Duration::days(2));
+
+        let ids: Vec<i32> = TodoList::load_tasks(path.clone())
+            .tasks
+            .iter()
+            .map(|task| task.id)
+            .collect();
+        assert_eq!(ids, vec![2, 3, 5]);
+        assert!(!todolist.archive_path().exists());
+        assert!(
+            todolist
+                .history()
+                .unwrap()
+                .iter()
+                .any(|line| line.ends_with("prune 4 task 4"))
+        );
+    }
+
+    #[test]
+    fn test_max_completed_archives_pruned_tasks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let mut todolist = TodoList::new(&path);
+        todolist.max_completed = Some(1);
+        todolist.archive_pruned = true;
+        let now = Local.with_ymd_and_hms(2024, 6, 10, 12, 0, 0).unwrap();
+        todolist.add_task("old".to_string(), None);
+        todolist.add_task("new".to_string(), None);
+        todolist.complete_task_at(1, now - chrono::Duration::days(2));
+        todolist.complete_task_at(2, now - chrono::Duration::days(1));
+
+        let saved = TodoList::load_tasks(path);
+        assert_eq!(saved.tasks.len(), 1);
+        assert_eq!(saved.tasks[0].title, "new");
+        let archive = TodoList::load_tasks(todolist.archive_path());
+        assert_eq!(archive.tasks.len(), 1);
+        assert_eq!(archive.tasks[0].title, "old");
+    }
+
+    #[test]
+    fn test_unusable_archive_keeps_the_tasks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        todolist.complete_task(1);
+
+        // Unparsable: left as it is, the task staying in the list
+        fs::write(todolist.archive_path(), "not json").unwrap();
+        assert_eq!(todolist.archive_tasks(|_| true), 0);
+        assert_eq!(
+            fs::read_to_string(todolist.archive_path()).unwrap(),
+            "not json"
+        );
+        assert_eq!(TodoList::load_tasks(path.clone()).tasks.len(), 1);
+
+        // Unwritable
+        fs::remove_file(todolist.archive_path()).unwrap();
+        fs::create_dir(todolist.archive_path()).unwrap();
+        assert_eq!(todolist.archive_tasks(|_| true), 0);
+        assert_eq!(TodoList::load_tasks(path.clone()).tasks.len(), 1);
+
+        todolist.max_completed = Some(0);
+        todolist.archive_pruned = true;
+        todolist.save_tasks();
+        assert_eq!(todolist.tasks.len(), 1);
+        assert_eq!(TodoList::load_tasks(path).tasks.len(), 1);
+    }
+
+    #[test]
+    fn test_transfer_task_moves_it_to_the_target_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let other = dir.path().join("other.json");
+        let mut target = TodoList::new(&other);
+        target.add_task("already there".to_string(), None);
+
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("stays".to_string(), None);
+        todolist.add_task("moves".to_string(), Some(PriorityEnum::High));
+        todolist.tag_task(2, &["work".to_string()], &[]);
+
+        assert_eq!(todolist.transfer_task(2, &other), Some(2));
+        let source = TodoList::load_tasks(path);
+        assert_eq!(source.tasks.len(), 1);
+        assert_eq!(source.tasks[0].title, "stays");
+
+        let target = TodoList::load_tasks(other);
+        assert_eq!(target.tasks.len(), 2);
+        let moved = &target.tasks[1];
+        assert_eq!(moved.id, 2);
+        assert_eq!(moved.title, "moves");
+        assert_eq!(moved.priority, Some(PriorityEnum::High));
+        assert_eq!(moved.tags, vec!["work".to_string()]);
+    }
+
+    #[test]
+    fn test_transfer_task_to_a_new_file_or_itself() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let other = dir.path().join("new.json");
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        todolist.add_task("task 2".to_string(), None);
+
+        assert_eq!(todolist.transfer_task(2, &path), None);
+        assert_eq!(todolist.transfer_task(3, &other), None);
+        assert_eq!(todolist.tasks.len(), 2);
+
+        assert_eq!(todolist.transfer_task(2, &other), Some(1));
+        let target = TodoList::load_tasks(other);
+        assert_eq!(target.tasks.len(), 1);
+        assert_eq!(target.tasks[0].title, "task 2");
+    }
+
+    #[test]
+    fn test_transfer_to_an_unreadable_list_changes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let other = dir.path().join("other.json");
+        fs::write(&other, "not json").unwrap();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+
+        assert_eq!(todolist.transfer_task(1, &other), None);
+        assert_eq!(fs::read_to_string(&other).unwrap(), "not json");
+        assert_eq!(todolist.tasks.len(), 1);
+        assert_eq!(TodoList::load_tasks(path).tasks.len(), 1);
+    }
+
+    #[test]
+    fn test_transfer_to_an_unwritable_list_keeps_the_task() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let other = dir.path().join("missing").join("dir").join("other.json");
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+
+        assert_eq!(todolist.transfer_task(1, &other), None);
+        assert!(!other.exists());
+        assert_eq!(todolist.tasks.len(), 1);
+        assert_eq!(TodoList::load_tasks(path).tasks.len(), 1);
+    }
+
+    #[test]
+    fn test_transfer_keeps_subtasks_with_their_parent() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let other = dir.path().join("other.json");
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("parent".to_string(), None);
+        let mut task = Task::new(todolist.next_id(), "subtask".to_string(), None);
+        task.parent_id = Some(1);
+        todolist.insert_task(task);
+
+        assert_eq!(todolist.transfer_task(1, &other), None);
+        assert_eq!(todolist.tasks.len(), 2);
+        assert!(!other.exists());
+
+        // A subtask moves alone, without its parent id
+        assert_eq!(todolist.transfer_task(2, &other), Some(1));
+        assert_eq!(TodoList::load_tasks(other).tasks[0].parent_id, None);
+    }
+
+    #[test]
+    fn test_restore_specific_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        todolist.backup_tasks_at(Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap());
+        todolist.add_task("task 2".to_string(), None);
+        todolist.backup_tasks_at(Local.with_ymd_and_hms(2024, 6, 2, 12, 0, 0).unwrap());
+        todolist.add_task("task 3".to_string(), None);
+        assert_eq!(
+            todolist.backups(),
+            vec!["20240601T120000000", "20240602T120000000"]
+        );
+
+        todolist.restore_backup(
+            Some("20240601T120000000".to_string()),
+            &mut "n\n".as_bytes(),
+        );
+        assert_eq!(todolist.tasks.len(), 3);
+        todolist.restore_backup(
+            Some("20240601T120000000".to_string()),
+            &mut "y\n".as_bytes(),
+        );
+        assert_eq!(todolist.tasks.len(), 1);
+        todolist.restore_backup(None, &mut "y\n".as_bytes());
+        assert_eq!(TodoList::load_tasks(path).tasks.len(), 2);
+    }
+
+    #[test]
+    fn test_restore_backup_brings_back_the_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let mut todolist = TodoList::new(&path);
+        todolist.set_name("work".to_string(), Some("Q2 goals".to_string()));
+        todolist.add_task("task 1".to_string(), None);
+        todolist.backup_tasks_at(Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap());
+        todolist.set_name("home".to_string(), Some("chores".to_string()));
+        todolist.color = true;
+
+        todolist.restore_backup(None, &mut "y\n".as_bytes());
+        let saved = TodoList::load_tasks(path);
+        for todolist in [&todolist, &saved] {
+            assert_eq!(todolist.name.as_deref(), Some("work"));
+            assert_eq!(todolist.description.as_deref(), Some("Q2 goals"));
+            assert_eq!(todolist.tasks.len(), 1);
+        }
+        assert!(todolist.color);
+    }
+
+    #[test]
+    fn test_restore_invalid_backup_keeps_tasks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        fs::write(dir.path().join("todo.json.broken.bak"), "not json").unwrap();
+        todolist.restore_backup(Some("broken".to_string()), &mut "y\n".as_bytes());
+        assert_eq!(todolist.tasks.len(), 1);
+    }
+
+    #[test]
+    fn test_stats_weighted_completion() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        todolist.add_task("task 2".to_string(), None);
+        todolist.add_task("task 3".to_string(), None);
+        todolist.add_task("task 4".to_string(), None);
+        todolist.complete_task(1);
+        todolist.set_progress(2, 50);
+        todolist.set_progress(3, 30);
+
+        let stats = todolist.stats();
+        assert_eq!(stats.total, 4);
+        assert_eq!(stats.done, 1);
+        assert_eq!(stats.completion, 25.0);
+        assert_eq!(stats.weighted_completion, 45.0);
+    }
+
+    #[test]
+    fn test_stats_by_priority() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        for priority in [
+            Some(PriorityEnum::High),
+            Some(PriorityEnum::High),
+            Some(PriorityEnum::High),
+            Some(PriorityEnum::Medium),
+            Some(PriorityEnum::Low),
+            Some(PriorityEnum::Low),
+            None,
+        ] {
+            todolist.add_task("task".to_string(), priority);
+        }
+        todolist.complete_tasks(&[1, 4, 5, 6]);
+
+        let counts: Vec<(usize, usize)> = todolist
+            .stats()
+            .by_priority
+            .iter()
+            .map(|counts| (counts.done, counts.pending))
+            .collect();
+        assert_eq!(counts, vec![(1, 2), (1, 0), (2, 0), (0, 1)]);
+        assert_eq!(
+            todolist.stats().render_by_priority(),
+            vec![
+                "Priority    done pending",
+                "high           1       2",
+                "medium         1       0",
+                "low            2       0",
+                "none           0       1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_journal_appends_completed_tasks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("write report".to_string(), None);
+        todolist.add_task("call bob".to_string(), None);
+        todolist.add_task("unjournaled".to_string(), None);
+        let now = Local.with_ymd_and_hms(2024, 6, 1, 9, 5, 0).unwrap();
+
+        todolist.complete_task_at(3, now);
+        let journal = dir.path().join("done-2024-06-01.md");
+        assert!(!journal.exists());
+
+        todolist.journal = true;
+        todolist.complete_task_at(1, now);
+        todolist.complete_task_at(2, now + chrono::Duration::hours(8));
+        assert_eq!(todolist.journal_path(now.date_naive()), journal);
+        assert_eq!(
+            fs::read_to_string(&journal).unwrap(),
+            "- 09:05 write report\n- 17:05 call bob\n"
+        );
+    }
+
+    #[test]
+    fn test_cascade_parent_completes_after_the_last_subtask() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("release".to_string(), None);
+        for (title, parent) in [("docs", 1), ("build", 1), ("linux", 3), ("mac", 3)] {
+            let mut task = Task::new(todolist.next_id(), title.to_string(), None);
+            task.parent_id = Some(parent);
+            todolist.insert_task(task);
+        }
+        let done = |todolist: &TodoList| -> Vec<i32> {
+            todolist.completed_tasks().map(|task| task.id).collect()
+        };
+
+        todolist.cascade_parent = true;
+        todolist.complete_task(2);
+        assert_eq!(done(&todolist), vec![2]);
+        todolist.complete_task(4);
+        assert_eq!(done(&todolist), vec![2, 4]);
+        // The last subtask completes its parent, which was the last one of the root
+        todolist.complete_task(5);
+        assert_eq!(done(&todolist), vec![1, 2, 3, 4, 5]);
+        assert_eq!(TodoList::load_tasks(path).completed_tasks().count(), 5);
+    }
+
+    #[test]
+    fn test_complete_instead_of_swaps_the_completion() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        for i in 1..=3 {
+            todolist.add_task(format!("task {}", i), None);
+        }
+        let now = Local.with_ymd_and_hms(2024, 6, 1, 9, 0, 0).unwrap();
+        todolist.complete_task_at(2, now);
+
+        let later = now + chrono::Duration::minutes(1);
+        assert!(todolist.complete_instead_of_at(3, 2, later));
+        let saved = TodoList::load_tasks(path);
+        for todolist in [&todolist, &saved] {
+            assert!(todolist.tasks[2].done);
+            assert_eq!(todolist.tasks[2].completed_at, Some(later));
+            assert!(!todolist.tasks[1].done);
+            assert_eq!(todolist.tasks[1].status, StatusEnum::Todo);
+            assert_eq!(todolist.tasks[1].completed_at, None);
+        }
+        let history = todolist.history().unwrap();
+        assert!(history.iter().any(|line| line.ends_with("reopen 2 task 2")));
+        assert!(
+            history
+                .iter()
+                .any(|line| line.ends_with("complete 3 task 3"))
+        );
+    }
+
+    #[test]
+    fn test_complete_instead_of_failures_change_nothing() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        todolist.add_task("task 2".to_string(), None);
+        todolist.complete_task(2);
+        let before = todolist.tasks.clone();
+
+        // An unknown task to complete, an unknown or pending task to reopen, or the same task
+        for (id, wrong) in [(9, 2), (1, 9), (2, 1), (2, 2)] {
+            assert!(!todolist.complete_instead_of(id, wrong), "{} {}", id, wrong);
+            assert_eq!(todolist.tasks, before);
+            assert_eq!(TodoList::load_tasks(path.clone()).tasks, before);
+        }
+    }
+
+    #[test]
+    fn test_without_cascade_parent_parents_stay_pending() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("release".to_string(), None);
+        let mut task = Task::new(todolist.next_id(), "docs".to_string(), None);
+        task.parent_id = Some(1);
+        todolist.insert_task(task);
+        todolist.complete_task(2);
+        assert!(!todolist.tasks[0].done);
+    }
+
+    #[test]
+    fn test_problems_of_inconsistent_lists() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        for i in 1..=4 {
+            todolist.add_task(format!("task {}", i), None);
+        }
+        todolist.complete_task(4);
+        assert!(todolist.problems().is_empty());
+
+        todolist.tasks[1].id = 1;
+        todolist.tasks[2].parent_id = Some(9);
+        todolist.tasks[3].done = false;
+        todolist.tasks[0].progress = 120;
+        assert_eq!(
+            todolist.problems(),
+            vec![
+                "Task id 1 is used more than once",
+                "Task 1 has a progress of 120%",
+                "Task 3 is a subtask of task 9, which does not exist",
+                "Task 4 is pending but has a completion date",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_problems_of_parent_cycles() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        for i in 1..=4 {
+            todolist.add_task(format!("task {}", i), None);
+        }
+        todolist.tasks[0].parent_id = Some(2);
+        todolist.tasks[1].parent_id = Some(1);
+        todolist.tasks[2].parent_id = Some(3);
+        todolist.tasks[3].parent_id = Some(1);
+        assert_eq!(
+            todolist.problems(),
+            vec![
+                "Task 1 is in a cycle of parents",
+                "Task 2 is in a cycle of parents",
+                "Task 3 is its own parent",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_writes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        fs::write(
+            &path,
+            r#"{"tasks":[{"id":1,"title":"old","done":true,"created_at":"2024-06-01T12:00:00Z","completed_at":null},
+                {"id":2,"title":"sub","done":false,"created_at":"2024-06-01T12:00:00Z","completed_at":null,"parent_id":3}]}"#,
+        )
+        .unwrap();
+        let before = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            TodoList::validate(&path).unwrap(),
+            vec!["Task 2 is a subtask of task 3, which does not exist"]
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), before);
+        assert!(TodoList::validate(&dir.path().join("missing.json")).is_err());
+    }
+
+    #[test]
+    fn test_time_sorts_break_ties_by_id() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        for i in 1..=20 {
+            todolist.add_task(format!("task {}", i), None);
+        }
+        let ids: Vec<i32> = (1..=20).collect();
+        todolist.sort = Some(SortEnum::Created);
+        let sorted = |todolist: &TodoList| -> Vec<i32> {
+            let view = todolist.view_tasks(todolist.tasks.iter());
+            view.iter().map(|task| task.id).collect()
+        };
+        assert_eq!(sorted(&todolist), ids);
+
+        // The same instant for every task, listed out of order
+        let created = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        let due = Local.with_ymd_and_hms(2024, 6, 7, 12, 0, 0).unwrap();
+        for task in todolist.tasks.iter_mut() {
+            task.created_at = created;
+            task.due_at = Some(due);
+        }
+        todolist.tasks.reverse();
+        todolist.tasks.swap(3, 11);
+        assert_eq!(sorted(&todolist), ids);
+        todolist.sort = Some(SortEnum::Due);
+        assert_eq!(sorted(&todolist), ids);
+
+        // Saved dates keep their nanoseconds
+        let precise = created + chrono::Duration::nanoseconds(1);
+        todolist.tasks[0].created_at = precise;
+        todolist.save_tasks();
+        let reloaded = TodoList::load_tasks(path);
+        assert_eq!(reloaded.tasks[0].created_at, precise);
+    }
+
+    #[test]
+    fn test_iter_filtered_matches_the_status_iterators() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        for i in 1..=6 {
+            todolist.add_task(format!("task {}", i), None);
+        }
+        todolist.complete_tasks(&[2, 3, 6]);
+        let ids = |tasks: Vec<&Task>| tasks.iter().map(|task| task.id).collect::<Vec<i32>>();
+
+        let completed = TaskFilter::new().completed();
+        assert_eq!(
+            ids(todolist.iter_filtered(&completed).collect()),
+            ids(todolist.completed_tasks().collect())
+        );
+        let pending = TaskFilter::new().pending();
+        assert_eq!(
+            ids(todolist.iter_filtered(&pending).collect()),
+            ids(todolist.pending_tasks().collect())
+        );
+        let all = TaskFilter::new();
+        assert_eq!(
+            ids(todolist.iter_filtered(&all).collect()),
+            ids(todolist.into_iter().collect())
+        );
+    }
+
+    #[test]
+    fn test_lead_time_on_completion() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        let created = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        for title in ["task 1", "task 2", "task 3"] {
+            let mut task = Task::new(todolist.next_id(), title.to_string(), None);
+            task.created_at = created;
+            todolist.insert_task(task);
+        }
+        assert_eq!(todolist.stats().average_lead_time_seconds, None);
+
+        todolist.complete_task_at(1, created + chrono::Duration::hours(2));
+        todolist.complete_task_at(2, created + chrono::Duration::hours(4));
+        assert_eq!(todolist.tasks[0].lead_time_seconds, Some(7200));
+        assert_eq!(todolist.tasks[1].lead_time_seconds, Some(14400));
+        assert_eq!(todolist.tasks[2].lead_time_seconds, None);
+        assert_eq!(todolist.stats().average_lead_time_seconds, Some(10800.0));
+        assert_eq!(
+            TodoList::load_tasks(path).tasks[1].lead_time_seconds,
+            Some(14400)
+        );
+
+        todolist.set_status(2, StatusEnum::Todo);
+        assert_eq!(todolist.tasks[1].lead_time_seconds, None);
+        assert_eq!(todolist.stats().average_lead_time_seconds, Some(7200.0));
+    }
+
+    #[test]
+    fn test_stats_json() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), Some(PriorityEnum::High));
+        todolist.add_task("task 2".to_string(), Some(PriorityEnum::High));
+        todolist.add_task("task 3".to_string(), None);
+        todolist.add_task("task 4".to_string(), Some(PriorityEnum::Low));
+        todolist.complete_task(1);
+        todolist.set_progress(2, 50);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&todolist.stats().to_json().unwrap()).unwrap();
+        assert_eq!(json["total"], 4);
+        assert_eq!(json["done"], 1);
+        assert_eq!(json["completion"], 25.0);
+        assert_eq!(json["weighted_completion"], 37.5);
+        let by_priority = json["by_priority"].as_array().unwrap();
+        assert_eq!(by_priority.len(), 4);
+        assert_eq!(
+            by_priority[0],
+            serde_json::json!({"priority": "High", "done": 1, "pending": 1})
+        );
+        assert_eq!(
+            by_priority[3],
+            serde_json::json!({"priority": null, "done": 0, "pending": 1})
+        );
+    }
+
+    #[test]
+    fn test_edit_task_keeps_title_history() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("first".to_string(), None);
+        todolist.edit_task(1, "second".to_string());
+        todolist.edit_task(1, "third".to_string());
+        assert_eq!(todolist.tasks[0].title, "third");
+        assert_eq!(todolist.tasks[0].title_history, vec!["first", "second"]);
+        assert!(
+            todolist.tasks[0]
+                .render_details()
+                .contains("Previous titles: first, second")
+        );
+
+        fs::remove_file(&path).unwrap();
+        todolist.edit_task(2, "fourth".to_string());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_priority_sort_places_none_priority() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("none".to_string(), None);
+        todolist.add_task("low".to_string(), Some(PriorityEnum::Low));
+        todolist.add_task("high".to_string(), Some(PriorityEnum::High));
+        todolist.sort = Some(SortEnum::Priority);
+        todolist.fields = vec![FieldEnum::Title];
+
+        assert_eq!(
+            todolist.render_tasks(todolist.tasks.iter()),
+            vec!["high", "low", "none"]
+        );
+        todolist.none_first = true;
+        assert_eq!(
+            todolist.render_tasks(todolist.tasks.iter()),
+            vec!["none", "high", "low"]
+        );
+    }
+
+    #[test]
+    fn test_touch_task_bumps_created_at() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), Some(PriorityEnum::High));
+        let created_at = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        todolist.tasks[0].created_at = created_at;
+        todolist.touch_task(1);
+        let task = &todolist.tasks[0];
+        assert!(task.created_at > created_at);
+        assert_eq!(task.title, "task 1");
+        assert_eq!(task.priority, Some(PriorityEnum::High));
+        assert!(!task.done);
+
+        fs::remove_file(&path).unwrap();
+        todolist.touch_task(2);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_markdown_export_shows_priority_and_due() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        let mut task = Task::new(
+            todolist.next_id(),
+            "Buy milk".to_string(),
+            Some(PriorityEnum::High),
+        );
+        task.due_at = Some(Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap());
+        todolist.insert_task(task);
+        todolist.export_tasks(FormatEnum::Markdown, &ExportOptions::default());
+
+        let markdown = fs::read_to_string(path.with_extension("md")).unwrap();
+        assert!(markdown.contains("- [ ] 🔴 Buy milk (due 2024-06-01)"));
+    }
+
+    #[test]
+    fn test_list_does_not_write_the_file() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+
+        let todolist = TodoList::load_tasks(path.clone());
+        todolist.list_tasks();
+        todolist.list_completed_tasks();
+        todolist.list_pending_tasks();
+        assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), modified);
+    }
+
+    #[test]
+    fn test_load_missing_file_does_not_create_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let todolist = TodoList::load_tasks(path.clone());
+        todolist.list_tasks();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_complete_all_pending_tasks() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        todolist.add_task("task 2".to_string(), None);
+        todolist.add_task("task 3".to_string(), None);
+        todolist.complete_task(2);
+
+        assert_eq!(todolist.complete_all_tasks(false, &mut "n\n".as_bytes()), 0);
+        assert_eq!(todolist.pending_tasks().count(), 2);
+        assert_eq!(todolist.complete_all_tasks(false, &mut "y\n".as_bytes()), 2);
+        assert_eq!(todolist.pending_tasks().count(), 0);
+        assert!(
+            todolist
+                .tasks
+                .iter()
+                .all(|task| task.completed_at.is_some())
+        );
+    }
+
+    #[test]
+    fn test_complete_all_logs_and_journals_each_task() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        todolist.add_task("task 2".to_string(), None);
+        todolist.journal = true;
+
+        assert_eq!(todolist.complete_all_tasks(true, &mut "".as_bytes()), 2);
+        let history = todolist.history().unwrap();
+        assert!(
+            history
+                .iter()
+                .any(|line| line.ends_with("complete 1 task 1"))
+        );
+        assert!(
+            history
+                .iter()
+                .any(|line| line.ends_with("complete 2 task 2"))
+        );
+        let journal = fs::read_to_string(todolist.journal_path(Local::now().date_naive())).unwrap();
+        assert_eq!(journal.lines().count(), 2);
+        assert!(journal.lines().all(|line| line.starts_with("- ")));
+    }
+
+    #[test]
+    fn test_complete_all_with_yes_skips_confirmation() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        assert_eq!(todolist.complete_all_tasks(true, &mut "".as_bytes()), 1);
+        assert!(todolist.tasks[0].done);
+    }
+
+    #[test]
+    fn test_purge_needs_the_typed_name() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        todolist.add_task("task 2".to_string(), None);
+        todolist.set_name("work".to_string(), None);
+
+        for answer in ["y\n", "purge\n", "Work\n", ""] {
+            assert_eq!(todolist.purge_tasks(false, &mut answer.as_bytes()), 0);
+        }
+        assert_eq!(TodoList::load_tasks(path.clone()).tasks.len(), 2);
+
+        assert_eq!(todolist.purge_tasks(false, &mut "work\n".as_bytes()), 2);
+        assert!(TodoList::load_tasks(path.clone()).tasks.is_empty());
+
+        todolist.add_task("task 3".to_string(), None);
+        assert_eq!(todolist.purge_tasks(false, &mut "PURGE\n".as_bytes()), 1);
+        assert!(todolist.tasks.is_empty());
+    }
+
+    #[test]
+    fn test_purge_with_yes_skips_confirmation() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        assert_eq!(todolist.purge_tasks(true, &mut "".as_bytes()), 1);
+        assert!(TodoList::load_tasks(path).tasks.is_empty());
+    }
+
+    #[test]
+    fn test_source_round_trip_and_filter() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        let mut task = Task::new(todolist.next_id(), "laptop task".to_string(), None);
+        task.source = Some("laptop".to_string());
+        todolist.insert_task(task);
+        let mut task = Task::new(todolist.next_id(), "desktop task".to_string(), None);
+        task.source = Some("desktop".to_string());
+        todolist.insert_task(task);
+        todolist.add_task("unknown task".to_string(), None);
+
+        let mut todolist = TodoList::load_tasks(path);
+        assert_eq!(todolist.tasks[0].source, Some("laptop".to_string()));
+        todolist.filter = TaskFilter::new().source("laptop");
+        todolist.fields = vec![FieldEnum::Title];
+        assert_eq!(
+            todolist.render_tasks(todolist.tasks.iter()),
+            vec!["laptop task"]
+        );
+    }
+
+    #[test]
+    fn test_reminders_due_only() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        let now = Local.with_ymd_and_hms(2024, 6, 10, 12, 0, 0).unwrap();
+        let mut task = Task::new(todolist.next_id(), "due".to_string(), None);
+        task.remind_at = Some(now - chrono::Duration::hours(1));
+        todolist.insert_task(task);
+        let mut task = Task::new(todolist.next_id(), "later".to_string(), None);
+        task.remind_at = Some(now + chrono::Duration::hours(1));
+        todolist.insert_task(task);
+        let mut task = Task::new(todolist.next_id(), "done".to_string(), None);
+        task.remind_at = Some(now - chrono::Duration::hours(1));
+        todolist.insert_task(task);
+        todolist.complete_task(3);
+
+        assert_eq!(todolist.reminders(now), vec!["⏰ Reminder: due"]);
+    }
+
+    #[test]
+    fn test_split_task_into_subtasks() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("big".to_string(), None);
+        todolist.add_task("other".to_string(), None);
+        todolist.split_task(
+            1,
+            vec![
+                "part 1".to_string(),
+                "part 2".to_string(),
+                "part 3".to_string(),
+            ],
+        );
+        assert_eq!(todolist.tasks.len(), 5);
+        for task in &todolist.tasks[2..] {
+            assert_eq!(task.parent_id, Some(1));
+            assert_eq!(task.source, hostname());
+        }
+        let history = todolist.history().unwrap();
+        let events: Vec<_> = history[2..]
+            .iter()
+            .map(|line| line.split_once(' ').unwrap().1)
+            .collect();
+        assert_eq!(events, vec!["add 3 part 1", "add 4 part 2", "add 5 part 3"]);
+
+        todolist.fields = vec![FieldEnum::Title];
+        assert_eq!(
+            todolist.render_tasks(todolist.tasks.iter()),
+            vec!["big", "  part 1", "  part 2", "  part 3", "other"]
+        );
+    }
+
+    #[test]
+    fn test_flat_ignores_nesting() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("big".to_string(), None);
+        todolist.add_task("other".to_string(), None);
+        todolist.split_task(1, vec!["part 1".to_string(), "part 2".to_string()]);
+
+        todolist.flat = true;
+        todolist.fields = vec![FieldEnum::Title];
+        assert_eq!(
+            todolist.render_tasks(todolist.tasks.iter()),
+            vec!["big", "other", "part 1", "part 2"]
+        );
+        todolist.fields = vec![];
+        for line in todolist.render_tasks(todolist.tasks.iter()) {
+            assert!(!line.starts_with(' '));
+        }
+    }
+
+    #[test]
+    fn test_split_unknown_task_adds_nothing() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.split_task(1, vec!["part 1".to_string()]);
+        assert!(todolist.tasks.is_empty());
+    }
+
+    #[test]
+    fn test_list_to_output_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut todolist = TodoList::new(&dir.path().join("todo.json"));
+        todolist.add_task("task 1".to_string(), None);
+        todolist.add_task("task 2".to_string(), None);
+        todolist.complete_task(2);
+        let report = dir.path().join("reports").join("daily").join("todo.txt");
+        todolist.output_path = Some(report.clone());
+
+        todolist.list_pending_tasks();
+        let expected: Vec<String> = todolist
+            .render_tasks(todolist.pending_tasks())
+            .into_iter()
+            .map(|line| line + "\n")
+            .collect();
+        assert_eq!(fs::read_to_string(&report).unwrap(), expected.concat());
+
+        todolist.output = OutputEnum::Porcelain;
+        todolist.list_tasks();
+        assert_eq!(fs::read_to_string(&report).unwrap().lines().count(), 2);
+        todolist.output = OutputEnum::JsonArray;
+        todolist.list_tasks();
+        let tasks: Vec<Task> = serde_json::from_str(&fs::read_to_string(&report).unwrap()).unwrap();
+        assert_eq!(tasks, todolist.tasks);
+    }
+
+    #[test]
+    fn test_write_tasks_matches_rendered_lines() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        for i in 1..=100 {
+            todolist.add_task(format!("task {}", i), None);
+        }
+        let mut out = vec![];
+        todolist
+            .write_tasks(&mut out, todolist.tasks.iter())
+            .unwrap();
+        let mut expected = todolist.render_tasks(todolist.tasks.iter()).join("\n");
+        expected.push('\n');
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_due_within_window() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        let now = Local.with_ymd_and_hms(2024, 6, 10, 12, 0, 0).unwrap();
+        for (title, due) in [
+            ("now", Some(now)),
+            ("within", Some(now + chrono::Duration::days(3))),
+            ("beyond", Some(now + chrono::Duration::days(10))),
+            ("overdue", Some(now - chrono::Duration::days(1))),
+            ("no due", None),
+        ] {
+            let mut task = Task::new(todolist.next_id(), title.to_string(), None);
+            task.due_at = due;
+            todolist.insert_task(task);
+        }
+        let duration = crate::duration::parse_duration("1w").unwrap();
+        todolist.filter = TaskFilter::new().pending().due_between(now, now + duration);
+        todolist.fields = vec![FieldEnum::Title];
+        assert_eq!(
+            todolist.render_tasks(todolist.tasks.iter()),
+            vec!["now", "within"]
+        );
+    }
+
+    #[test]
+    fn test_export_to_explicit_out_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        let out = dir.path().join("exports").join("tasks.csv");
+        todolist.export_tasks(
+            FormatEnum::Csv,
+            &ExportOptions {
+                out: Some(out.clone()),
+                ..Default::default()
+            },
+        );
+
+        assert!(out.exists());
+        assert!(!path.with_extension("csv").exists());
+        assert!(fs::read_to_string(out).unwrap().contains("task 1"));
+    }
+
+    #[test]
+    fn test_load_task_without_priority() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        fs::write(
+            &path,
+            r#"{"tasks":[{"id":1,"title":"task 1","done":false,"created_at":"2024-06-01T09:00:00+00:00","completed_at":null}]}"#,
+        )
+        .unwrap();
+        let todolist = TodoList::load_tasks(path);
+        assert_eq!(todolist.tasks.len(), 1);
+        assert_eq!(todolist.tasks[0].priority, None);
+    }
+
+    #[test]
+    fn test_json_output_compact_and_pretty() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        todolist.add_task("task 2".to_string(), None);
+        todolist.complete_task(2);
+
+        todolist.output = OutputEnum::Json;
+        let mut out = vec![];
+        todolist
+            .write_tasks(&mut out, todolist.pending_tasks())
+            .unwrap();
+        let compact = String::from_utf8(out).unwrap();
+        assert!(!compact.trim_end().contains('\n'));
+        let listing: serde_json::Value = serde_json::from_str(&compact).unwrap();
+        assert_eq!(listing["version"], 1);
+        assert_eq!(listing["count"], 1);
+        let tasks: Vec<Task> = serde_json::from_value(listing["tasks"].clone()).unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].title, "task 1");
+
+        todolist.output = OutputEnum::JsonPretty;
+        let mut out = vec![];
+        todolist
+            .write_tasks(&mut out, todolist.pending_tasks())
+            .unwrap();
+        let pretty = String::from_utf8(out).unwrap();
+        assert!(pretty.trim_end().contains('\n'));
+        let listing: serde_json::Value = serde_json::from_str(&pretty).unwrap();
+        assert_eq!(listing["count"], 1);
+        assert_eq!(listing["tasks"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_json_count_and_array_output() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        for i in 1..=3 {
+            todolist.add_task(format!("task {}", i), None);
+        }
+
+        todolist.output = OutputEnum::Json;
+        let mut out = vec![];
+        todolist
+            .write_tasks(&mut out, todolist.tasks.iter())
+            .unwrap();
+        let listing: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(listing["count"], 3);
+        assert_eq!(listing["tasks"].as_array().unwrap().len(), 3);
+
+        todolist.output = OutputEnum::JsonArray;
+        let mut out = vec![];
+        todolist
+            .write_tasks(&mut out, todolist.tasks.iter())
+            .unwrap();
+        let tasks: Vec<Task> = serde_json::from_slice(&out).unwrap();
+        assert_eq!(tasks.len(), 3);
+    }
+
+    #[test]
+    fn test_link_task_is_saved() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("fix login".to_string(), None);
+        let ticket = "https://example.com/issues/12".to_string();
+
+        let links = todolist.link_task(1, std::slice::from_ref(&ticket), &[]);
+        assert_eq!(links, Some(vec![ticket.clone()]));
+        assert_eq!(todolist.link_task(2, &[], &[]), None);
+        assert_eq!(
+            TodoList::load_tasks(path.clone()).tasks[0].links,
+            vec![ticket.clone()]
+        );
+
+        todolist.link_task(1, &[], &[ticket]);
+        assert!(TodoList::load_tasks(path.clone()).tasks[0].links.is_empty());
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(todolist.link_task(2, &[], &[]), None);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_defer_task_is_saved() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        let until = Local.with_ymd_and_hms(2024, 6, 3, 9, 0, 0).unwrap();
+
+        todolist.defer_task(1, Some(until));
+        assert_eq!(
+            TodoList::load_tasks(path.clone()).tasks[0].hide_until,
+            Some(until)
+        );
+
+        fs::remove_file(&path).unwrap();
+        todolist.defer_task(2, Some(until));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_tag_task() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+
+        let tags = todolist.tag_task(1, &["home".to_string(), "home".to_string()], &[]);
+        assert_eq!(tags, Some(vec!["home".to_string()]));
+
+        let tags = todolist.tag_task(1, &["work".to_string()], &[]);
+        assert_eq!(tags, Some(vec!["home".to_string(), "work".to_string()]));
+
+        let tags = todolist.tag_task(1, &[], &["home".to_string()]);
+        assert_eq!(tags, Some(vec!["work".to_string()]));
+
+        let tags = todolist.tag_task(1, &["urgent".to_string()], &["work".to_string()]);
+        assert_eq!(tags, Some(vec!["urgent".to_string()]));
+
+        let todolist = TodoList::load_tasks(path);
+        assert_eq!(todolist.tasks[0].tags, vec!["urgent".to_string()]);
+    }
+
+    #[test]
+    fn test_tag_task_unknown_id() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(todolist.tag_task(1, &["home".to_string()], &[]), None);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_clear_completed_tasks_before() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("old".to_string(), None);
+        todolist.add_task("recent".to_string(), None);
+        todolist.add_task("pending".to_string(), None);
+        todolist.complete_task_at(1, Local.with_ymd_and_hms(2024, 1, 10, 12, 0, 0).unwrap());
+        todolist.complete_task_at(2, Local.with_ymd_and_hms(2024, 3, 10, 12, 0, 0).unwrap());
+
+        let cutoff = Local.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap();
+        assert_eq!(todolist.clear_completed_tasks(Some(cutoff)), 1);
+        let titles: Vec<_> = todolist.tasks.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, vec!["recent", "pending"]);
+
+        assert_eq!(todolist.clear_completed_tasks(None), 1);
+        let titles: Vec<_> = todolist.tasks.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, vec!["pending"]);
+
+        let history = todolist.history().unwrap();
+        let events: Vec<_> = history[history.len() - 2..]
+            .iter()
+            .map(|line| line.split_once(' ').unwrap().1)
+            .collect();
+        assert_eq!(events, vec!["remove 1 old", "remove 2 recent"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_mode_applied_on_save() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let mut todolist = TodoList::empty(&path);
+        todolist.file_mode = Some(0o600);
+        todolist.journal = true;
+        todolist.add_task("task 1".to_string(), None);
+        let now = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        todolist.complete_task_at(1, now);
+
+        for file in [
+            path.clone(),
+            dir.path().join("todo.json.log"),
+            todolist.journal_path(now.date_naive()),
+        ] {
+            let mode = fs::metadata(&file).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600, "{}", file.display());
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_save_writes_through_symlink() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("data")).unwrap();
+        let target = dir.path().join("data").join("todo.json");
+        let link = dir.path().join("todo.json");
+        std::os::unix::fs::symlink("data/todo.json", &link).unwrap();
+
+        let mut todolist = TodoList::new(&link);
+        todolist.add_task("task 1".to_string(), None);
+        todolist.add_task("task 2".to_string(), None);
+
+        assert!(
+            fs::symlink_metadata(&link)
+                .unwrap()
+                .file_type()
+                .is_symlink()
+        );
+        assert_eq!(TodoList::load_tasks(target).tasks.len(), 2);
+        assert_eq!(TodoList::load_tasks(link).tasks.len(), 2);
+    }
+
+    #[test]
+    fn test_duplicate_groups() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("Buy milk".to_string(), None);
+        todolist.add_task("Walk dog".to_string(), None);
+        todolist.add_task("  buy MILK ".to_string(), None);
+        todolist.add_task("walk dog".to_string(), None);
+        todolist.add_task("Unique".to_string(), None);
+        todolist.complete_task(4);
+
+        let groups: Vec<Vec<i32>> = todolist
+            .duplicate_groups()
+            .iter()
+            .map(|group| group.iter().map(|task| task.id).collect())
+            .collect();
+        assert_eq!(groups, vec![vec![1, 3]]);
+    }
+
+    #[test]
+    fn test_remove_duplicates_keeps_oldest() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("Buy milk".to_string(), None);
+        todolist.add_task("buy milk".to_string(), None);
+        todolist.add_task("Buy Milk".to_string(), None);
+        todolist.tasks[0].created_at = Local.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap();
+        todolist.tasks[1].created_at = Local.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        todolist.tasks[2].created_at = Local.with_ymd_and_hms(2024, 1, 3, 0, 0, 0).unwrap();
+
+        assert_eq!(todolist.remove_duplicates(), 2);
+        let ids: Vec<_> = todolist.tasks.iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![2]);
+        assert!(todolist.duplicate_groups().is_empty());
+
+        let history = todolist.history().unwrap();
+        let events: Vec<_> = history[3..]
+            .iter()
+            .map(|line| line.split_once(' ').unwrap().1)
+            .collect();
+        assert_eq!(events, vec!["remove 1 Buy milk", "remove 3 Buy Milk"]);
+    }
+
+    #[test]
+    fn test_renumber_ids() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        let parent = Task::new(4, "parent".to_string(), None);
+        let mut child = Task::new(9, "child".to_string(), None);
+        child.parent_id = Some(4);
+        let mut orphan = Task::new(12, "orphan".to_string(), None);
+        orphan.parent_id = Some(7);
+        todolist.tasks = vec![parent, child, orphan];
+
+        todolist.renumber_ids();
+        let ids: Vec<_> = todolist.tasks.iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert_eq!(todolist.tasks[1].parent_id, Some(1));
+        assert_eq!(todolist.tasks[2].parent_id, None);
+
+        let todolist = TodoList::load_tasks(path);
+        assert_eq!(todolist.tasks[1].parent_id, Some(1));
+    }
+
+    #[test]
+    fn test_done_window() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        for title in ["before", "first day", "last day", "after", "pending"] {
+            todolist.add_task(title.to_string(), None);
+        }
+        todolist.complete_task_at(1, Local.with_ymd_and_hms(2024, 5, 31, 23, 0, 0).unwrap());
+        todolist.complete_task_at(2, Local.with_ymd_and_hms(2024, 6, 1, 0, 30, 0).unwrap());
+        todolist.complete_task_at(3, Local.with_ymd_and_hms(2024, 6, 30, 23, 30, 0).unwrap());
+        todolist.complete_task_at(4, Local.with_ymd_and_hms(2024, 7, 1, 8, 0, 0).unwrap());
+
+        todolist.filter = TaskFilter::new().completed_between(
+            NaiveDate::from_ymd_opt(2024, 6, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 6, 30).unwrap(),
+        );
+        let titles: Vec<_> = todolist
+            .view_tasks(todolist.tasks.iter())
+            .iter()
+            .map(|t| t.title.as_str())
+            .collect();
+        assert_eq!(titles, vec!["first day", "last day"]);
+    }
+
+    #[test]
+    fn test_event_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let mut todolist = TodoList::new(&path);
+        assert!(todolist.history().unwrap().is_empty());
+
+        todolist.add_task("task 1".to_string(), None);
+        todolist.add_task("task 2".to_string(), None);
+        assert_eq!(todolist.history().unwrap().len(), 2);
+
+        let now = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        todolist.complete_task_at(1, now);
+        todolist.edit_task(2, "renamed".to_string());
+        todolist.remove_task(1);
+        todolist.edit_task(42, "missing".to_string());
+
+        let history = todolist.history().unwrap();
+        let events: Vec<_> = history
+            .iter()
+            .map(|line| line.split_once(' ').unwrap().1)
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                "add 1 task 1",
+                "add 2 task 2",
+                "complete 1 task 1",
+                "edit 2 task 2 -> renamed",
+                "remove 1 task 1",
+            ]
+        );
+        assert!(history[2].starts_with(&now.to_rfc3339()));
+
+        let reloaded = TodoList::load_tasks(path);
+        assert_eq!(reloaded.history().unwrap(), history);
+    }
+
+    #[test]
+    fn test_to_json_and_yaml_values() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        todolist.add_task("task 2".to_string(), Some(PriorityEnum::High));
+
+        let value = serde_json::Value::try_from(&todolist).unwrap();
+        let object = value.as_object().unwrap();
+        assert_eq!(object.keys().collect::<Vec<_>>(), vec!["tasks"]);
+        assert_eq!(value["tasks"].as_array().unwrap().len(), 2);
+        assert_eq!(value["tasks"][1]["title"], "task 2");
+
+        let value = todolist.to_yaml_value().unwrap();
+        let mapping = value.as_mapping().unwrap();
+        assert_eq!(mapping.len(), 1);
+        assert_eq!(value["tasks"].as_sequence().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_porcelain_separator() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), Some(PriorityEnum::High));
+        todolist.add_task("task 2".to_string(), None);
+
+        todolist.output = OutputEnum::Porcelain;
+        let mut out = vec![];
+        todolist
+            .write_tasks(&mut out, todolist.tasks.iter())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "1\t❌\tHigh\t\ttask 1\n2\t❌\t\t\ttask 2\n"
+        );
+
+        todolist.separator = " | ".to_string();
+        todolist.fields = vec![FieldEnum::Id, FieldEnum::Title];
+        let mut out = vec![];
+        todolist
+            .write_tasks(&mut out, todolist.tasks.iter())
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "1 | task 1\n2 | task 2\n");
+
+        todolist.output = OutputEnum::Null;
+        todolist.separator = ",".to_string();
+        let mut out = vec![];
+        todolist
+            .write_tasks(&mut out, todolist.tasks.iter())
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "1,task 1\x002,task 2\x00");
+    }
+
+    #[test]
+    fn test_status_migrated_from_done() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let created_at = "2024-06-01T12:00:00+00:00";
+        fs::write(
+            &path,
+            format!(
+                r#"{{"tasks":[
+                    {{"id":1,"title":"done","done":true,"created_at":"{0}","completed_at":"{0}"}},
+                    {{"id":2,"title":"pending","done":false,"created_at":"{0}"}}
+                ]}}"#,
+                created_at
+            ),
+        )
+        .unwrap();
+        let todolist = TodoList::load_tasks(path);
+        assert_eq!(todolist.tasks[0].status, StatusEnum::Done);
+        assert_eq!(todolist.tasks[1].status, StatusEnum::Todo);
+    }
+
+    #[test]
+    fn test_set_status() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+
+        todolist.set_status(1, StatusEnum::Waiting);
+        assert_eq!(todolist.tasks[0].status, StatusEnum::Waiting);
+        assert!(!todolist.tasks[0].done);
+
+        todolist.set_status(1, StatusEnum::Done);
+        assert!(todolist.tasks[0].done);
+        assert!(todolist.tasks[0].completed_at.is_some());
+
+        todolist.set_status(1, StatusEnum::Doing);
+        assert!(!todolist.tasks[0].done);
+        assert_eq!(todolist.tasks[0].completed_at, None);
+
+        todolist.complete_task(1);
+        assert_eq!(todolist.tasks[0].status, StatusEnum::Done);
+        todolist.reset_tasks();
+        assert_eq!(todolist.tasks[0].status, StatusEnum::Todo);
+
+        let mut todolist = TodoList::load_tasks(path.clone());
+        assert_eq!(todolist.tasks[0].status, StatusEnum::Todo);
+
+        fs::remove_file(&path).unwrap();
+        todolist.set_status(2, StatusEnum::Doing);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_list_name_round_trip_and_header() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("milk".to_string(), None);
+        todolist.set_name("Groceries".to_string(), Some("For the weekend".to_string()));
+        todolist.set_name("Shopping".to_string(), None);
+
+        let mut todolist = TodoList::load_tasks(path);
+        assert_eq!(todolist.name, Some("Shopping".to_string()));
+        assert_eq!(todolist.description, Some("For the weekend".to_string()));
+
+        todolist.fields = vec![FieldEnum::Title];
+        let mut out = vec![];
+        todolist
+            .write_tasks(&mut out, todolist.tasks.iter())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Shopping\nFor the weekend\n\nmilk\n"
+        );
+    }
+
+    #[test]
+    fn test_unnamed_list_has_no_header() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("milk".to_string(), None);
+        assert!(!fs::read_to_string(&path).unwrap().contains("name"));
+
+        todolist.fields = vec![FieldEnum::Title];
+        let mut out = vec![];
+        todolist
+            .write_tasks(&mut out, todolist.tasks.iter())
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "milk\n");
+    }
+
+    #[test]
+    fn test_complete_and_remove_by_id() {
+        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+        let mut todolist = TodoList::new(&path);
+        todolist.insert_task(Task::new(5, "task 5".to_string(), None));
+        todolist.insert_task(Task::new(9, "task 9".to_string(), None));
+
+        for id in [-1, 0, 1, 2, i32::MAX, i32::MIN] {
+            todolist.complete_task(id);
+            todolist.remove_task(id);
+        }
+        assert_eq!(todolist.tasks.len(), 2);
+        assert!(todolist.tasks.iter().all(|task| !task.done));
+
+        todolist.complete_task(9);
+        assert!(!todolist.tasks[0].done);
+        assert!(todolist.tasks[1].done);
+
+        todolist.remove_task(5);
+        let ids: Vec<_> = todolist.tasks.iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![9]);
     }
 
-    pub fn add_task(&mut self, title: String, priority: Option<PriorityEnum>) {
-        let mut last_task_id = 1;
-        if let Some(last_task) = self.tasks.last() {
-            last_task_id = last_task.id + 1;
-        }
-        let task = Task {
-            id: last_task_id,
-            title,
-            done: false,
-            created_at: Local::now(),
-            completed_at: None,
-            priority,
-        };
-        self.tasks.push(task);
-        self.save_tasks();
+    #[derive(Default, Debug)]
+    struct CountingStore {
+        inner: MemoryStore,
+        saves: std::rc::Rc<std::cell::Cell<usize>>,
     }
 
-    pub fn remove_task(&mut self, i: i32) {
-        if let Ok(index) = usize::try_from(i - 1)
-            && let Some(_) = self.tasks.get_mut(index)
-        {
-            self.tasks.remove(index);
+    impl Store for CountingStore {
+        fn load(&self) -> Result<Option<TodoList>, LoadError> {
+            self.inner.load()
         }
-        self.save_tasks();
-    }
 
-    pub fn list_tasks(&mut self) {
-        for task in self.tasks.iter() {
-            task.display();
+        fn save(&self, todolist: &TodoList) -> Result<(), ExportError> {
+            self.saves.set(self.saves.get() + 1);
+            self.inner.save(todolist)
         }
-    }
 
-    pub fn list_completed_tasks(&mut self) {
-        for task in self.completed_tasks() {
-            task.display();
+        fn append_log(&self, todolist: &TodoList, line: &str) -> io::Result<()> {
+            self.inner.append_log(todolist, line)
         }
-    }
 
-    pub fn list_pending_tasks(&mut self) {
-        for task in self.pending_tasks() {
-            task.display();
+        fn read_log(&self) -> io::Result<Vec<String>> {
+            self.inner.read_log()
         }
     }
 
-    pub fn complete_task(&mut self, i: i32) {
-        if let Ok(index) = usize::try_from(i - 1)
-            && let Some(task) = self.tasks.get_mut(index)
-        {
-            task.done = true;
-            task.completed_at = Some(Local::now())
+    #[test]
+    fn test_complete_several_tasks_saves_once() {
+        let store = CountingStore::default();
+        let saves = store.saves.clone();
+        let mut todolist = TodoList::open(Path::new("counting.json"), Box::new(store)).unwrap();
+        for title in ["task 1", "task 2", "task 3", "task 4"] {
+            todolist.add_task(title.to_string(), None);
         }
-        self.save_tasks();
+        saves.set(0);
+
+        assert!(!todolist.complete_tasks(&[1, 3, 4, 42]));
+        assert_eq!(saves.get(), 1);
+        let done: Vec<_> = todolist.tasks.iter().map(|t| t.done).collect();
+        assert_eq!(done, vec![true, false, true, true]);
     }
 
-    pub fn reset_tasks(&mut self) {
-        for task in self.tasks.iter_mut() {
-            task.done = false;
-            task.completed_at = None;
-        }
-        self.save_tasks();
+    #[test]
+    fn test_unknown_ids_are_not_saved() {
+        let store = CountingStore::default();
+        let saves = store.saves.clone();
+        let mut todolist = TodoList::open(Path::new("counting.json"), Box::new(store)).unwrap();
+        todolist.add_task("task 1".to_string(), None);
+        saves.set(0);
+
+        assert!(!todolist.complete_tasks(&[42]));
+        assert!(!todolist.complete_and_remove_tasks(&[42]));
+        assert!(!todolist.remove_task(42));
+        assert_eq!(saves.get(), 0);
+        assert_eq!(todolist.tasks.len(), 1);
+        assert!(!todolist.tasks[0].done);
     }
 
-    pub fn export_tasks(&self, format: FormatEnum) {
-        let exporter: Box<dyn Exporter> = match format {
-            FormatEnum::Json => Box::new(JsonExporter),
-            FormatEnum::Csv => Box::new(CsvExporter),
-            FormatEnum::Yaml => Box::new(YamlExporter),
-            FormatEnum::Markdown => Box::new(MarkdownExporter),
+    #[test]
+    fn test_export_append_accumulates() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        todolist.add_task("task 2".to_string(), None);
+        let append = ExportOptions {
+            append: true,
+            ..Default::default()
         };
 
-        match exporter.export(self) {
-            Ok(_) => (),
-            Err(ExportError::SerializationError(msg)) => {
-                eprintln!("Serialization failed {}", msg);
-            }
-            Err(ExportError::IoError(e)) => {
-                eprintln!("IO error {}", e);
-            }
-        }
+        todolist.export_tasks(FormatEnum::Csv, &append);
+        todolist.export_tasks(FormatEnum::Csv, &append);
+        let csv = fs::read_to_string(path.with_extension("csv")).unwrap();
+        assert_eq!(csv.lines().count(), 5);
+        assert_eq!(csv.matches("id,title").count(), 1);
+        let rows =
+            importer::import(&path.with_extension("csv"), FormatEnum::Csv).unwrap_or_default();
+        assert_eq!(rows.len(), 4);
+
+        todolist.export_tasks(FormatEnum::Markdown, &append);
+        todolist.export_tasks(FormatEnum::Markdown, &append);
+        let markdown = fs::read_to_string(path.with_extension("md")).unwrap();
+        assert_eq!(markdown.matches("## Pending").count(), 2);
+        assert_eq!(markdown.matches("task 1").count(), 2);
+
+        todolist.export_tasks(FormatEnum::Jsonl, &ExportOptions::default());
+        todolist.export_tasks(FormatEnum::Jsonl, &append);
+        let tasks =
+            importer::import(&path.with_extension("jsonl"), FormatEnum::Jsonl).unwrap_or_default();
+        assert_eq!(tasks.len(), 4);
     }
 
-    pub fn save_tasks(&self) {
-        self.export_tasks(FormatEnum::Json);
+    #[test]
+    fn test_export_append_refused_for_documents() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+
+        let append = ExportOptions {
+            append: true,
+            ..Default::default()
+        };
+        todolist.export_tasks(FormatEnum::Yaml, &append);
+        assert!(!path.with_extension("yaml").exists());
+        let result = JsonExporter::default().append(&todolist, &path);
+        assert!(matches!(result, Err(ExportError::AppendUnsupported)));
     }
 
-    pub fn load_tasks(path: PathBuf) -> Self {
-        match fs::read_to_string(&path) {
-            Ok(content) => {
-                let mut todolist: TodoList =
-                    serde_json::from_str(&content).unwrap_or_else(|_| TodoList::new(&path));
-                todolist.path = path;
-                todolist
-            }
-            Err(_) => TodoList::new(&path),
+    #[test]
+    fn test_auto_escalate_raises_old_pending_tasks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let mut todolist = TodoList::new(&path);
+        let now = Local.with_ymd_and_hms(2024, 6, 30, 12, 0, 0).unwrap();
+        todolist.add_task("fresh".to_string(), Some(PriorityEnum::Low));
+        todolist.add_task("stale low".to_string(), Some(PriorityEnum::Low));
+        todolist.add_task("stale medium".to_string(), Some(PriorityEnum::Medium));
+        todolist.add_task("stale high".to_string(), Some(PriorityEnum::High));
+        todolist.add_task("stale done".to_string(), Some(PriorityEnum::Low));
+        todolist.add_task("stale none".to_string(), None);
+        for (task, age) in todolist.tasks.iter_mut().zip([2, 10, 10, 10, 10, 10]) {
+            task.created_at = now - chrono::Duration::days(age);
         }
+        todolist.complete_task_at(5, now - chrono::Duration::days(1));
+
+        assert_eq!(todolist.auto_escalate(7, now), 2);
+        let priorities: Vec<_> = todolist.tasks.iter().map(|t| t.priority.clone()).collect();
+        assert_eq!(
+            priorities,
+            vec![
+                Some(PriorityEnum::Low),
+                Some(PriorityEnum::Medium),
+                Some(PriorityEnum::High),
+                Some(PriorityEnum::High),
+                Some(PriorityEnum::Low),
+                None,
+            ]
+        );
+        assert_eq!(todolist.tasks[1].escalated_at, Some(now));
+        assert_eq!(todolist.tasks[1].modified_at, Some(now));
+        assert_eq!(todolist.tasks[3].escalated_at, None);
+        assert_ne!(todolist.tasks[3].modified_at, Some(now));
+        assert!(
+            todolist
+                .history()
+                .unwrap()
+                .last()
+                .unwrap()
+                .ends_with("escalate 3 High")
+        );
+
+        // Escalated tasks wait another full period before the next level
+        assert_eq!(
+            todolist.auto_escalate(7, now + chrono::Duration::days(3)),
+            0
+        );
+        assert_eq!(
+            todolist.auto_escalate(7, now + chrono::Duration::days(8)),
+            2
+        );
+        assert_eq!(todolist.tasks[0].priority, Some(PriorityEnum::Medium));
+        assert_eq!(todolist.tasks[1].priority, Some(PriorityEnum::High));
+        let saved = TodoList::load_tasks(path);
+        assert_eq!(saved.tasks[1].priority, Some(PriorityEnum::High));
+        assert!(saved.tasks[1].escalated_at.is_some());
     }
 
-    pub fn completed_tasks<'a>(&'a self) -> CompletedTasksIter<'a> {
-        CompletedTasksIter {
-            inner: self.tasks.iter(),
+    #[test]
+    fn test_review_applies_scripted_actions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let mut todolist = TodoList::new(&path);
+        for title in [
+            "done",
+            "complete me",
+            "remove me",
+            "prioritize me",
+            "snooze me",
+            "skip me",
+            "never reached",
+        ] {
+            todolist.add_task(title.to_string(), None);
         }
+        todolist.complete_task(1);
+        let now = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+
+        // An unknown action and an unknown priority are asked again
+        let mut input = io::Cursor::new("c\nr\nx\np\nurgent\np\nHigh\ns\n3d\n\nq\n");
+        assert_eq!(todolist.review_tasks(&mut input, now), 4);
+
+        let titles: Vec<&str> = todolist.tasks.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(
+            titles,
+            vec![
+                "done",
+                "complete me",
+                "prioritize me",
+                "snooze me",
+                "skip me",
+                "never reached"
+            ]
+        );
+        assert!(todolist.tasks[1].done);
+        assert_eq!(todolist.tasks[2].priority, Some(PriorityEnum::High));
+        assert_eq!(
+            todolist.tasks[3].due_at,
+            Some(now + chrono::Duration::days(3))
+        );
+        assert_eq!(todolist.tasks[4].priority, None);
+        assert!(!todolist.tasks[5].done);
+
+        let saved = TodoList::load_tasks(path);
+        assert_eq!(saved.tasks.len(), 6);
+        assert_eq!(saved.tasks[2].priority, Some(PriorityEnum::High));
     }
 
-    pub fn pending_tasks<'a>(&'a self) -> PendingTasksIter<'a> {
-        PendingTasksIter {
-            inner: self.tasks.iter(),
-        }
+    #[test]
+    fn test_review_stops_at_end_of_input() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut todolist = TodoList::new(&dir.path().join("todo.json"));
+        todolist.add_task("task 1".to_string(), None);
+        todolist.add_task("task 2".to_string(), None);
+
+        let mut input = io::Cursor::new("c\n");
+        assert_eq!(todolist.review_tasks(&mut input, Local::now()), 1);
+        assert!(todolist.tasks[0].done);
+        assert!(!todolist.tasks[1].done);
     }
-}
 
-impl<'a> IntoIterator for &'a TodoList {
-    type Item = &'a Task;
-    type IntoIter = std::slice::Iter<'a, Task>;
-    fn into_iter(self) -> Self::IntoIter {
-        self.tasks.iter()
+    #[test]
+    fn test_export_sorted_by_priority() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("low".to_string(), Some(PriorityEnum::Low));
+        todolist.add_task("none".to_string(), None);
+        todolist.add_task("high".to_string(), Some(PriorityEnum::High));
+        todolist.add_task("medium".to_string(), Some(PriorityEnum::Medium));
+
+        todolist.export_tasks(
+            FormatEnum::Csv,
+            &ExportOptions {
+                sort: Some(SortEnum::Priority),
+                ..Default::default()
+            },
+        );
+        let mut csv = csv::Reader::from_path(path.with_extension("csv")).unwrap();
+        let titles: Vec<String> = csv
+            .deserialize::<CsvTask>()
+            .map(|row| row.unwrap().title)
+            .collect();
+        assert_eq!(titles, vec!["high", "medium", "low", "none"]);
+
+        let ids: Vec<i32> = todolist.tasks.iter().map(|task| task.id).collect();
+        assert_eq!(ids, vec![1, 2, 3, 4]);
+        assert_eq!(TodoList::load_tasks(path).tasks[0].title, "low");
     }
-}
 
-pub struct CompletedTasksIter<'a> {
-    inner: std::slice::Iter<'a, Task>,
-}
+    #[test]
+    fn test_exports_are_in_id_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let mut todolist = TodoList::new(&path);
+        for i in 1..=5 {
+            todolist.add_task(format!("task {}", i), Some(PriorityEnum::Low));
+        }
+        todolist.tasks.swap(0, 3);
+        todolist.tasks.swap(1, 4);
+        let shuffled: Vec<i32> = todolist.tasks.iter().map(|task| task.id).collect();
+        assert_eq!(shuffled, vec![4, 5, 3, 1, 2]);
 
-impl<'a> Iterator for CompletedTasksIter<'a> {
-    type Item = &'a Task;
+        for format in [
+            FormatEnum::Json,
+            FormatEnum::Yaml,
+            FormatEnum::Csv,
+            FormatEnum::Jsonl,
+        ] {
+            todolist.export_tasks(format.clone(), &ExportOptions::default());
+            let exported = importer::import(&format.default_path(&path), format.clone()).unwrap();
+            let ids: Vec<i32> = exported.iter().map(|task| task.id).collect();
+            assert_eq!(ids, vec![1, 2, 3, 4, 5], "{:?}", format);
+        }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.inner.by_ref().find(|&task| task.done).map(|v| v as _)
+        // Ties of an explicit sort fall back to id order too
+        let out = dir.path().join("sorted.csv");
+        todolist.export_tasks(
+            FormatEnum::Csv,
+            &ExportOptions {
+                out: Some(out.clone()),
+                sort: Some(SortEnum::Priority),
+                ..Default::default()
+            },
+        );
+        let ids: Vec<i32> = importer::import(&out, FormatEnum::Csv)
+            .unwrap()
+            .iter()
+            .map(|task| task.id)
+            .collect();
+        assert_eq!(ids, vec![1, 2, 3, 4, 5]);
     }
-}
 
-pub struct PendingTasksIter<'a> {
-    inner: std::slice::Iter<'a, Task>,
-}
+    #[test]
+    fn test_export_refuses_to_overwrite_save_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.csv");
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        let saved = fs::read_to_string(&path).unwrap();
 
-impl<'a> Iterator for PendingTasksIter<'a> {
-    type Item = &'a Task;
+        let result = todolist.export(FormatEnum::Csv, &ExportOptions::default());
+        assert!(matches!(result, Err(ExportError::WouldOverwriteSave(_))));
+        let out = ExportOptions {
+            out: Some(dir.path().join(".").join("todo.csv")),
+            ..Default::default()
+        };
+        let result = todolist.export(FormatEnum::Csv, &out);
+        assert!(matches!(result, Err(ExportError::WouldOverwriteSave(_))));
+        assert_eq!(fs::read_to_string(&path).unwrap(), saved);
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.inner.by_ref().find(|&task| !task.done).map(|v| v as _)
+        assert!(
+            todolist
+                .export(FormatEnum::Yaml, &ExportOptions::default())
+                .is_ok()
+        );
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use tempfile::NamedTempFile;
-    use super::*;
+    #[test]
+    fn test_export_force_overwrites_save_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.csv");
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+
+        let force = ExportOptions {
+            force: true,
+            ..Default::default()
+        };
+        assert!(todolist.export(FormatEnum::Csv, &force).is_ok());
+        assert!(fs::read_to_string(&path).unwrap().starts_with("id,title"));
+    }
 
     #[test]
-    fn test_new_todolist_is_empty() {
+    fn test_complete_and_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("throwaway".to_string(), None);
+        todolist.add_task("keep".to_string(), None);
+        let now = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+
+        todolist.complete_and_remove_tasks_at(&[1, 7], now);
+        let titles: Vec<&str> = todolist.tasks.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, vec!["keep"]);
+        assert_eq!(todolist.stats().total, 1);
+        assert_eq!(TodoList::load_tasks(path).tasks.len(), 1);
+
+        let history = todolist.history().unwrap();
+        let events: Vec<&str> = history
+            .iter()
+            .map(|line| line.split_once(' ').unwrap().1)
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                "add 1 throwaway",
+                "add 2 keep",
+                "complete 1 throwaway",
+                "remove 1 throwaway"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_export_json_indent() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("task 1".to_string(), None);
+        let out = dir.path().join("export.json");
+        let export = |indent: Option<&str>| {
+            todolist.export_tasks(
+                FormatEnum::Json,
+                &ExportOptions {
+                    out: Some(out.clone()),
+                    indent: indent.map(str::to_string),
+                    ..Default::default()
+                },
+            );
+            fs::read_to_string(&out).unwrap()
+        };
+
+        assert!(export(None).starts_with("{\n  \"tasks\": [\n    {\n      \"id\": 1,"));
+        assert!(
+            export(Some("    "))
+                .starts_with("{\n    \"tasks\": [\n        {\n            \"id\": 1,")
+        );
+        assert!(export(Some("\t")).starts_with("{\n\t\"tasks\": [\n\t\t{\n\t\t\t\"id\": 1,"));
+        assert_eq!(TodoList::load_tasks(out.clone()).tasks.len(), 1);
+    }
+
+    #[test]
+    fn test_add_built_task() {
         let path = NamedTempFile::new().unwrap().path().to_path_buf();
-        let todolist = TodoList::new(&path);
-        assert_eq!(todolist.tasks.len(), 0);
-        assert_eq!(&todolist.path, &path);
+        let mut todolist = TodoList::new(&path);
+        todolist.add_task("first".to_string(), None);
+        let id = todolist.add(TaskBuilder::new().title("second").tag("home"));
+        assert_eq!(id, 2);
+        let saved = TodoList::load_tasks(path);
+        assert_eq!(saved.tasks[1].title, "second");
+        assert_eq!(saved.tasks[1].tags, vec!["home"]);
     }
 
     #[test]
-    fn test_add_task_increase_length() {
+    fn test_add_all_numbers_the_tasks_in_order() {
         let path = NamedTempFile::new().unwrap().path().to_path_buf();
         let mut todolist = TodoList::new(&path);
-        todolist.add_task("task 1".to_string(), None);
-        assert_eq!(todolist.tasks.len(), 1);
-        todolist.add_task("task 2".to_string(), None);
-        assert_eq!(todolist.tasks.len(), 2);
+        todolist.add_task("first".to_string(), None);
+        let builder = TaskBuilder::new().tag("errands");
+        let ids = todolist.add_all(
+            ["milk", "bread"]
+                .into_iter()
+                .map(|title| builder.clone().title(title)),
+        );
+        assert_eq!(ids, vec![2, 3]);
+        let saved = TodoList::load_tasks(path);
+        let titles: Vec<&str> = saved.tasks.iter().map(|task| task.title.as_str()).collect();
+        assert_eq!(titles, vec!["first", "milk", "bread"]);
+        assert_eq!(saved.tasks[2].tags, vec!["errands"]);
     }
 
     #[test]
-    fn test_remove_task_decrease_length() {
+    fn test_list_limit() {
         let path = NamedTempFile::new().unwrap().path().to_path_buf();
         let mut todolist = TodoList::new(&path);
-        todolist.add_task("task 1".to_string(), None);
-        todolist.add_task("task 2".to_string(), None);
-        todolist.remove_task(2);
-        assert_eq!(todolist.tasks.len(), 1);
+        todolist.add_task("low".to_string(), Some(PriorityEnum::Low));
+        todolist.add_task("high".to_string(), Some(PriorityEnum::High));
+        todolist.add_task("medium".to_string(), Some(PriorityEnum::Medium));
+        todolist.add_task("none".to_string(), None);
+        todolist.sort = Some(SortEnum::Priority);
+        todolist.fields = vec![FieldEnum::Title];
+        todolist.limit = Some(2);
+
+        let mut out = vec![];
+        todolist
+            .write_tasks(&mut out, todolist.tasks.iter())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "high\nmedium\n...and 2 more\n"
+        );
+
+        todolist.output = OutputEnum::Porcelain;
+        let mut out = vec![];
+        todolist
+            .write_tasks(&mut out, todolist.tasks.iter())
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "high\nmedium\n");
+
+        todolist.output = OutputEnum::Text;
+        todolist.limit = Some(4);
+        let mut out = vec![];
+        todolist
+            .write_tasks(&mut out, todolist.tasks.iter())
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap().lines().count(), 4);
     }
 
     #[test]
-    fn test_add_tasks_with_priority() {
+    fn test_confirm_completion_with_pending_subtasks() {
         let path = NamedTempFile::new().unwrap().path().to_path_buf();
         let mut todolist = TodoList::new(&path);
-        todolist.add_task("task 1".to_string(), Some(PriorityEnum::High));
-        todolist.add_task("task 2".to_string(), Some(PriorityEnum::Medium));
-        todolist.add_task("task 2".to_string(), Some(PriorityEnum::Low));
-        todolist.add_task("task 2".to_string(), None);
+        todolist.add_task("parent".to_string(), None);
+        todolist.add_task("single".to_string(), None);
+        todolist.split_task(1, vec!["child".to_string(), "other child".to_string()]);
+        todolist.split_task(3, vec!["grandchild".to_string()]);
+        todolist.complete_task(4);
+
+        let titles: Vec<&str> = todolist
+            .pending_subtasks(1)
+            .iter()
+            .map(|t| t.title.as_str())
+            .collect();
+        assert_eq!(titles, vec!["child", "grandchild"]);
+
+        // Only the parent asks, so a single answer is read
         assert_eq!(
-            todolist.tasks.get(0).unwrap().priority,
-            Some(PriorityEnum::High)
+            todolist.confirm_completion(&[1, 2], false, &mut "n\n".as_bytes()),
+            vec![2]
         );
         assert_eq!(
-            todolist.tasks.get(1).unwrap().priority,
-            Some(PriorityEnum::Medium)
+            todolist.confirm_completion(&[1, 2], false, &mut "y\n".as_bytes()),
+            vec![1, 2]
         );
         assert_eq!(
-            todolist.tasks.get(2).unwrap().priority,
-            Some(PriorityEnum::Low)
+            todolist.confirm_completion(&[1], true, &mut "".as_bytes()),
+            vec![1]
         );
-        assert_eq!(todolist.tasks.get(3).unwrap().priority, None);
     }
 
     #[test]
-    fn test_task_ids_are_incremented() {
+    fn test_confirm_completion_with_done_subtasks() {
         let path = NamedTempFile::new().unwrap().path().to_path_buf();
         let mut todolist = TodoList::new(&path);
-        todolist.add_task("task 1".to_string(), None);
-        assert_eq!(todolist.tasks.get(0).unwrap().id, 1);
-        todolist.add_task("task 2".to_string(), None);
-        assert_eq!(todolist.tasks.get(1).unwrap().id, 2);
+        todolist.add_task("parent".to_string(), None);
+        todolist.split_task(1, vec!["child".to_string()]);
+        todolist.complete_task(2);
+
+        assert!(todolist.pending_subtasks(1).is_empty());
+        assert_eq!(
+            todolist.confirm_completion(&[1], false, &mut "".as_bytes()),
+            vec![1]
+        );
     }
 
     #[test]
-    fn test_complete_task_changes_status() {
+    fn test_modified_today() {
         let path = NamedTempFile::new().unwrap().path().to_path_buf();
         let mut todolist = TodoList::new(&path);
-        todolist.add_task("task 1".to_string(), None);
-        assert_eq!(todolist.tasks.get(0).unwrap().done, false);
-        todolist.complete_task(1);
-        assert_eq!(todolist.tasks.get(0).unwrap().done, true);
+        for title in ["edited", "completed", "untouched", "tagged"] {
+            todolist.add_task(title.to_string(), None);
+        }
+        let yesterday = Local::now() - chrono::Duration::days(1);
+        for task in todolist.tasks.iter_mut() {
+            task.created_at = yesterday;
+            task.modified_at = Some(yesterday);
+        }
+        todolist.edit_task(1, "edited again".to_string());
+        todolist.complete_task(2);
+        todolist.tag_task(4, &["home".to_string()], &[]);
+
+        todolist.filter = TaskFilter::new().modified_on(Local::now().date_naive());
+        let titles: Vec<&str> = todolist
+            .view_tasks(todolist.tasks.iter())
+            .iter()
+            .map(|t| t.title.as_str())
+            .collect();
+        assert_eq!(titles, vec!["edited again", "completed", "tagged"]);
     }
 
     #[test]
-    fn test_complete_task_changes_completed_at() {
-        let path = NamedTempFile::new().unwrap().path().to_path_buf();
-        let mut todolist = TodoList::new(&path);
-        todolist.add_task("task 1".to_string(), None);
-        assert_eq!(todolist.tasks.get(0).unwrap().completed_at, None);
-        todolist.complete_task(1);
-        assert_ne!(todolist.tasks.get(0).unwrap().completed_at, None);
+    fn test_modified_at_backfilled_from_created_at() {
+        let file = NamedTempFile::new().unwrap();
+        fs::write(
+            file.path(),
+            r#"{"tasks":[{"id":1,"title":"old","done":false,"created_at":"2024-06-01T12:00:00+00:00","completed_at":null}]}"#,
+        )
+        .unwrap();
+        let todolist = TodoList::load_tasks(file.path().to_path_buf());
+        assert_eq!(
+            todolist.tasks[0].modified_at,
+            Some(todolist.tasks[0].created_at)
+        );
     }
 
     #[test]
-    fn test_completed_task_iterator() {
+    fn test_pick_task_same_seed_same_task() {
         let path = NamedTempFile::new().unwrap().path().to_path_buf();
         let mut todolist = TodoList::new(&path);
-        todolist.add_task("task 1".to_string(), None);
-        todolist.add_task("task 2".to_string(), None);
-        todolist.add_task("task 3".to_string(), None);
-        let completed_tasks: Vec<&Task> = todolist.completed_tasks().collect();
-        assert_eq!(completed_tasks.len(), 0);
-        todolist.complete_task(2);
-        todolist.complete_task(3);
-        let completed_tasks: Vec<&Task> = todolist.completed_tasks().collect();
-        assert_eq!(completed_tasks.len(), 2);
+        for i in 1..=20 {
+            todolist.add_task(format!("task {}", i), None);
+        }
+        todolist.complete_tasks(&[1, 2, 3]);
+
+        let pick = |seed| {
+            todolist
+                .pick_task(&mut fastrand::Rng::with_seed(seed), false)
+                .map(|t| t.id)
+        };
+        let first = pick(7).unwrap();
+        assert!(first > 3);
+        for _ in 0..5 {
+            assert_eq!(pick(7), Some(first));
+        }
+        let picks: HashSet<i32> = (0..50).filter_map(pick).collect();
+        assert!(picks.len() > 1);
+        assert!(picks.iter().all(|&id| id > 3));
+
+        todolist.complete_all_tasks(true, &mut "".as_bytes());
+        assert!(
+            todolist
+                .pick_task(&mut fastrand::Rng::with_seed(7), false)
+                .is_none()
+        );
     }
 
     #[test]
-    fn test_pending_task_iterator() {
+    fn test_pick_task_weighted() {
         let path = NamedTempFile::new().unwrap().path().to_path_buf();
         let mut todolist = TodoList::new(&path);
-        todolist.add_task("task 1".to_string(), None);
-        todolist.add_task("task 2".to_string(), None);
-        todolist.add_task("task 3".to_string(), None);
-        let pending_tasks: Vec<&Task> = todolist.pending_tasks().collect();
-        assert_eq!(pending_tasks.len(), 3);
-        todolist.complete_task(2);
-        todolist.complete_task(3);
-        let pending_tasks: Vec<&Task> = todolist.pending_tasks().collect();
-        assert_eq!(pending_tasks.len(), 1);
+        todolist.add_task("high".to_string(), Some(PriorityEnum::High));
+        todolist.add_task("medium".to_string(), Some(PriorityEnum::Medium));
+        todolist.add_task("low".to_string(), Some(PriorityEnum::Low));
+        todolist.add_task("none".to_string(), None);
+        todolist.add_task("done".to_string(), Some(PriorityEnum::High));
+        todolist.complete_task(5);
+
+        let mut rng = fastrand::Rng::with_seed(42);
+        assert_eq!(todolist.pick_task(&mut rng, true).unwrap().title, "medium");
+
+        // Weights 3, 2, 1 and 1 out of 7
+        let mut counts = HashMap::new();
+        for _ in 0..7000 {
+            let id = todolist.pick_task(&mut rng, true).unwrap().id;
+            *counts.entry(id).or_insert(0) += 1;
+        }
+        assert_eq!(counts.get(&5), None);
+        for (id, expected) in [(1, 3000), (2, 2000), (3, 1000), (4, 1000)] {
+            let count = counts[&id];
+            assert!(
+                (expected - 250..=expected + 250).contains(&count),
+                "task {} picked {} times",
+                id,
+                count
+            );
+        }
     }
 
     #[test]
-    fn test_reset_all_tasks() {
+    fn test_set_progress_is_capped_and_saved() {
         let path = NamedTempFile::new().unwrap().path().to_path_buf();
         let mut todolist = TodoList::new(&path);
         todolist.add_task("task 1".to_string(), None);
-        todolist.add_task("task 2".to_string(), None);
-        todolist.add_task("task 3".to_string(), None);
-        todolist.complete_task(1);
-        todolist.complete_task(2);
-        todolist.reset_tasks();
-        let pending_tasks: Vec<&Task> = todolist.pending_tasks().collect();
-        assert_eq!(pending_tasks.len(), 3);
+
+        todolist.set_progress(1, 150);
+        assert_eq!(TodoList::load_tasks(path.clone()).tasks[0].progress, 100);
+
+        fs::remove_file(&path).unwrap();
+        todolist.set_progress(2, 50);
+        assert!(!path.exists());
     }
 
     #[test]
-    fn test_save_and_load() {
-        let path = NamedTempFile::new().unwrap().path().to_path_buf();
+    fn test_add_progress_completes_at_100() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todo.json");
         let mut todolist = TodoList::new(&path);
+        todolist.journal = true;
         todolist.add_task("task 1".to_string(), None);
-        todolist.add_task("task 2".to_string(), Some(PriorityEnum::High));
-        todolist.add_task("task 3".to_string(), None);
-        todolist.complete_task(2);
-        todolist.save_tasks();
+        let now = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
 
-        let content = fs::read_to_string(&path).unwrap();
-        let loaded: TodoList = serde_json::from_str(&content).unwrap();
+        todolist.add_progress_at(1, 40, now);
+        todolist.add_progress_at(1, 40, now);
+        assert_eq!(todolist.tasks[0].progress, 80);
+        assert!(!todolist.tasks[0].done);
 
-        assert_eq!(loaded.tasks.len(), 3);
-        assert_eq!(loaded.tasks[0].title, "task 1");
-        assert_eq!(loaded.tasks[0].done, false);
-        assert_eq!(loaded.tasks[1].title, "task 2");
-        assert_eq!(loaded.tasks[1].done, true);
-        assert_eq!(loaded.tasks[1].priority, Some(PriorityEnum::High));
-        assert_ne!(loaded.tasks[1].completed_at, None);
-        assert_eq!(loaded.tasks[2].title, "task 3");
-        assert_eq!(loaded.tasks[2].done, false);
+        todolist.add_progress_at(1, 40, now);
+        assert_eq!(todolist.tasks[0].progress, 100);
+        assert!(todolist.tasks[0].done);
+        assert_eq!(todolist.tasks[0].completed_at, Some(now));
+
+        todolist.add_progress_at(1, 100, now + chrono::Duration::days(1));
+        assert_eq!(todolist.tasks[0].progress, 100);
+        assert_eq!(todolist.tasks[0].completed_at, Some(now));
+        let saved = TodoList::load_tasks(path.clone());
+        assert_eq!(saved.tasks[0].progress, 100);
+        assert!(saved.tasks[0].done);
+        assert_eq!(
+            fs::read_to_string(todolist.journal_path(now.date_naive())).unwrap(),
+            "- 12:00 task 1\n"
+        );
+
+        fs::remove_file(&path).unwrap();
+        todolist.add_progress_at(2, 40, now);
+        assert!(!path.exists());
     }
 }
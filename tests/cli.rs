@@ -0,0 +1,295 @@
+use std::{
+    fs,
+    path::Path,
+    process::{Command, Output},
+};
+
+fn todo(path: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_todo-cli"))
+        .arg("--path")
+        .arg(path)
+        .arg("--config")
+        .arg(path.with_extension("toml"))
+        .arg("--no-reminders")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_silent_skips_listing() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("todo.json");
+
+    let output = todo(&path, &["add", "task 1", "--silent"]);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+
+    let output = todo(&path, &["complete", "1", "--silent"]);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+
+    let output = todo(&path, &["list"]);
+    assert!(
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .contains("✅ task 1")
+    );
+
+    let output = todo(&path, &["remove", "1", "--silent"]);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_complete_lists_by_default() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("todo.json");
+    todo(&path, &["add", "task 1", "--silent"]);
+
+    let output = todo(&path, &["complete", "1"]);
+    assert!(String::from_utf8(output.stdout).unwrap().contains("task 1"));
+}
+
+#[test]
+fn test_default_list_filter_from_config() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("todo.json");
+    fs::write(
+        path.with_extension("toml"),
+        "default_list_filter = \"pending\"",
+    )
+    .unwrap();
+    todo(&path, &["add", "pending task", "--silent"]);
+    todo(&path, &["add", "done task", "--silent"]);
+    todo(&path, &["complete", "2", "--silent"]);
+
+    let stdout = String::from_utf8(todo(&path, &["list"]).stdout).unwrap();
+    assert!(stdout.contains("pending task"));
+    assert!(!stdout.contains("done task"));
+
+    let stdout = String::from_utf8(todo(&path, &["list", "--completed"]).stdout).unwrap();
+    assert!(!stdout.contains("pending task"));
+    assert!(stdout.contains("done task"));
+}
+
+#[test]
+fn test_random_with_seed_repeats() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("todo.json");
+    for i in 1..=10 {
+        todo(&path, &["add", &format!("task {}", i), "--silent"]);
+    }
+
+    let first = todo(&path, &["random", "--seed", "42"]);
+    assert!(first.status.success());
+    assert!(String::from_utf8_lossy(&first.stdout).contains("task"));
+    let second = todo(&path, &["--seed", "42", "random"]);
+    assert_eq!(first.stdout, second.stdout);
+}
+
+#[test]
+fn test_multi_line_title_is_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("todo.json");
+    todo(&path, &["add", "task 1", "--silent"]);
+
+    let output = todo(&path, &["add", "buy milk\ncall bob", "--silent"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--split-lines"));
+    let tasks: Vec<serde_json::Value> =
+        serde_json::from_slice(&todo(&path, &["list", "--json-array"]).stdout).unwrap();
+    assert_eq!(tasks.len(), 1);
+}
+
+#[test]
+fn test_split_lines_adds_a_task_per_line() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("todo.json");
+
+    let output = todo(
+        &path,
+        &[
+            "add",
+            "buy milk\r\n\n  call bob\n",
+            "--split-lines",
+            "--tag",
+            "errands",
+            "--silent",
+        ],
+    );
+    assert!(output.status.success());
+    let tasks: Vec<serde_json::Value> =
+        serde_json::from_slice(&todo(&path, &["list", "--json-array"]).stdout).unwrap();
+    let titles: Vec<&str> = tasks
+        .iter()
+        .map(|task| task["title"].as_str().unwrap())
+        .collect();
+    assert_eq!(titles, vec!["buy milk", "call bob"]);
+    assert_eq!(tasks[1]["id"], 2);
+    assert_eq!(tasks[1]["tags"], serde_json::json!(["errands"]));
+}
+
+#[test]
+fn test_deferred_task_hidden_until_its_date() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("todo.json");
+    todo(&path, &["add", "now", "--silent"]);
+    todo(&path, &["add", "later", "--silent"]);
+    todo(&path, &["add", "past", "--silent"]);
+    assert!(todo(&path, &["defer", "2", "2999-01-01"]).status.success());
+    assert!(
+        todo(&path, &["defer", "3", "2000-01-01 09:30"])
+            .status
+            .success()
+    );
+
+    let stdout = String::from_utf8(todo(&path, &["list"]).stdout).unwrap();
+    assert!(stdout.contains("now"));
+    assert!(!stdout.contains("later"));
+    assert!(stdout.contains("past"));
+
+    let stdout = String::from_utf8(todo(&path, &["list", "--show-deferred"]).stdout).unwrap();
+    assert!(stdout.contains("later"));
+
+    todo(&path, &["defer", "2"]);
+    let stdout = String::from_utf8(todo(&path, &["list"]).stdout).unwrap();
+    assert!(stdout.contains("later"));
+}
+
+#[test]
+fn test_validate_exit_codes() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("todo.json");
+    todo(&path, &["add", "task 1", "--silent"]);
+    todo(&path, &["add", "task 2", "--silent", "--parent", "1"]);
+    let checked = dir.path().join("checked.json");
+    let validate = |content: &str| {
+        fs::write(&checked, content).unwrap();
+        todo(&path, &["validate", checked.to_str().unwrap()])
+    };
+
+    let output = validate(&fs::read_to_string(&path).unwrap());
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("is valid"));
+
+    let task = r#"{"id":1,"title":"a","done":false,"created_at":"2024-06-01T12:00:00Z","completed_at":null}"#;
+    for invalid in [
+        "not json".to_string(),
+        format!(r#"{{"tasks":[{}],"colour":"red"}}"#, task),
+        format!(r#"{{"tasks":[{0},{0}]}}"#, task),
+        format!(
+            r#"{{"tasks":[{}]}}"#,
+            task.replace("\"done\"", "\"parent_id\":7,\"done\"")
+        ),
+    ] {
+        let output = validate(&invalid);
+        assert_eq!(output.status.code(), Some(1), "{}", invalid);
+        assert!(!output.stderr.is_empty());
+        assert_eq!(fs::read_to_string(&checked).unwrap(), invalid);
+    }
+
+    fs::remove_file(&checked).unwrap();
+    let output = todo(&path, &["validate", checked.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(!checked.exists());
+}
+
+#[test]
+fn test_complete_instead_of_failures_leave_the_file_unchanged() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("todo.json");
+    for title in ["task 1", "task 2", "task 3"] {
+        todo(&path, &["add", title, "--silent"]);
+    }
+    todo(&path, &["complete", "2", "--silent"]);
+    let saved = fs::read_to_string(&path).unwrap();
+
+    let output = todo(&path, &["complete", "3", "--instead-of", "9"]);
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("No task with id 9")
+    );
+    assert_eq!(fs::read_to_string(&path).unwrap(), saved);
+
+    let output = todo(&path, &["complete", "9", "--instead-of", "2"]);
+    assert!(!output.status.success());
+    assert_eq!(fs::read_to_string(&path).unwrap(), saved);
+
+    // Declining the pending subtasks prompt, stdin being closed, aborts
+    todo(&path, &["add", "subtask", "--parent", "3", "--silent"]);
+    let saved = fs::read_to_string(&path).unwrap();
+    let output = todo(&path, &["complete", "3", "--instead-of", "2"]);
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .contains("Aborted")
+    );
+    assert_eq!(fs::read_to_string(&path).unwrap(), saved);
+
+    let output = todo(
+        &path,
+        &["complete", "3", "--instead-of", "2", "--yes", "--silent"],
+    );
+    assert!(output.status.success());
+    let output = todo(&path, &["list", "--porcelain", "--fields", "id,status"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("2\t❌"));
+    assert!(stdout.contains("3\t✅"));
+}
+
+#[test]
+fn test_unparsable_save_file_is_not_overwritten() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("todo.json");
+    let content = r#"{"tasks":[{"id":1,"title":"task 1","done":false,"created_at":"2024-06-01T09:00:00+00:00","completed_at":null,"priority":"Urgent"}]}"#;
+    fs::write(&path, content).unwrap();
+
+    let output = todo(&path, &["add", "task 2", "--silent"]);
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("changes were not saved")
+    );
+    assert_eq!(fs::read_to_string(&path).unwrap(), content);
+}
+
+#[test]
+fn test_failed_transfer_exits_non_zero() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("todo.json");
+    todo(&path, &["add", "task 1", "--silent"]);
+    let saved = fs::read_to_string(&path).unwrap();
+
+    let other = dir.path().join("missing").join("dir").join("other.json");
+    let output = todo(&path, &["transfer", "1", other.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert_eq!(fs::read_to_string(&path).unwrap(), saved);
+}
+
+#[test]
+fn test_unknown_task_exits_non_zero_without_saving() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("todo.json");
+    todo(&path, &["add", "task 1", "--silent"]);
+    let saved = fs::read_to_string(&path).unwrap();
+
+    for args in [
+        &["complete", "99"][..],
+        &["complete", "99", "--remove"],
+        &["remove", "99"],
+    ] {
+        let output = todo(&path, args);
+        assert!(!output.status.success());
+        assert!(
+            String::from_utf8(output.stderr)
+                .unwrap()
+                .contains("No task with id 99")
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), saved);
+    }
+}